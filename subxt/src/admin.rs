@@ -0,0 +1,161 @@
+use subxt::{tx::Signer, utils::AccountId32, OnlineClient};
+use tracing::info;
+
+use crate::{
+    asset_balance, burn_token_call,
+    cli::{AdminAction, BatchMode},
+    clear_metadata_call, dry_run_batch_calls,
+    error::AppError,
+    freeze_account_call, freeze_asset_call, local, set_min_balance_call, sign_and_send_batch_calls, thaw_account_call,
+    thaw_asset_call, transfer_ownership_call, CustomConfig, MortalityConfig, SubmitPolicy,
+};
+
+// The `admin` subcommand: submits one of the asset admin calls and checks it
+// emitted the event it's expected to. The signer has to hold the asset's
+// freezer role to freeze, its admin role to thaw or burn and be its owner to
+// hand it over. Burning and handing the asset over are checked against the
+// chain first, see `burn` and `transfer_ownership`. With `validate_only` the
+// call is validated instead of submitted, after those checks.
+pub async fn run<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    action: &AdminAction,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let (call, expected) = match action {
+        AdminAction::Burn { asset_id, amount, from } => {
+            let who = from.clone().unwrap_or_else(|| signer.account_id());
+            return burn(api, signer, policy, *asset_id, who, *amount, tip, mortality, validate_only).await;
+        }
+        AdminAction::TransferOwnership { asset_id, new_owner } => {
+            return transfer_ownership(api, signer, policy, *asset_id, new_owner.clone(), tip, mortality, validate_only).await;
+        }
+        AdminAction::Freeze { asset_id, account: None } => (freeze_asset_call(*asset_id), "AssetFrozen"),
+        AdminAction::Thaw { asset_id, account: None } => (thaw_asset_call(*asset_id), "AssetThawed"),
+        AdminAction::Freeze { asset_id, account: Some(who) } => {
+            (freeze_account_call(*asset_id, who.clone().into()), "Frozen")
+        }
+        AdminAction::Thaw { asset_id, account: Some(who) } => (thaw_account_call(*asset_id, who.clone().into()), "Thawed"),
+        AdminAction::SetMinBalance { asset_id, min_balance } => {
+            (set_min_balance_call(*asset_id, *min_balance), "AssetMinBalanceChanged")
+        }
+        AdminAction::ClearMetadata { asset_id } => (clear_metadata_call(*asset_id), "MetadataCleared"),
+    };
+
+    if validate_only {
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api.clone(), signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let emitted = events
+        .iter()
+        .filter_map(Result::ok)
+        .any(|event| event.pallet_name() == "Assets" && event.variant_name() == expected);
+    if !emitted {
+        return Err(AppError::UnexpectedOutcome(format!("no `{expected}` event was emitted")));
+    }
+
+    // The metadata should be gone from storage as of the block it was cleared in
+    if let AdminAction::ClearMetadata { asset_id } = action {
+        let metadata = local::storage().assets().metadata(asset_id);
+        if api.storage().at_latest().await?.fetch(&metadata).await?.is_some() {
+            return Err(AppError::UnexpectedOutcome(format!(
+                "the metadata of the asset {asset_id} is still in storage"
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+// Hands the asset over to `new_owner` and waits for the `OwnerChanged` event.
+// The asset has to exist, be owned by the signer and not already belong to
+// `new_owner`, which is checked before anything is signed.
+#[allow(clippy::too_many_arguments)]
+async fn transfer_ownership<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    new_owner: AccountId32,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let asset = local::storage().assets().asset(asset_id);
+    let details = api
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&asset)
+        .await?
+        .ok_or_else(|| AppError::Invalid(format!("there's no asset {asset_id}")))?;
+
+    if details.owner != signer.account_id() {
+        return Err(AppError::Invalid(format!(
+            "the asset {asset_id} is owned by {}, not by the signer",
+            details.owner
+        )));
+    }
+    if details.owner == new_owner {
+        return Err(AppError::Invalid(format!("the asset {asset_id} is already owned by {new_owner}")));
+    }
+
+    let call = transfer_ownership_call(asset_id, new_owner.clone().into());
+    if validate_only {
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api, signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let changed = events
+        .find_first::<local::assets::events::OwnerChanged>()?
+        .ok_or_else(|| AppError::UnexpectedOutcome("no `OwnerChanged` event was emitted".into()))?;
+    if changed.owner != new_owner {
+        return Err(AppError::UnexpectedOutcome(format!(
+            "the asset {asset_id} ended up owned by {}",
+            changed.owner
+        )));
+    }
+
+    info!(asset_id, owner = %new_owner, "Transferred the ownership of the asset");
+    Ok(())
+}
+
+// Burns `amount` of the asset held by `who` and waits for the `Burned` event.
+// The signer has to be the asset's admin, and `who` has to hold at least
+// `amount`, which is checked first rather than failing with `BalanceLow`.
+#[allow(clippy::too_many_arguments)]
+async fn burn<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    who: AccountId32,
+    amount: u128,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let balance = asset_balance(&api, asset_id, &who).await?;
+    if amount > balance {
+        return Err(AppError::Invalid(format!(
+            "can't burn {amount} of the asset {asset_id}, {who} only holds {balance}"
+        )));
+    }
+
+    let call = burn_token_call(asset_id, who.clone().into(), amount);
+    if validate_only {
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api, signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let burned = events
+        .find_first::<local::assets::events::Burned>()?
+        .ok_or_else(|| AppError::UnexpectedOutcome("no `Burned` event was emitted".into()))?;
+    info!(amount = burned.balance, asset_id, who = %who, "Burned the asset");
+
+    Ok(())
+}
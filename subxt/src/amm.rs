@@ -0,0 +1,218 @@
+// Client side versions of the constant product math `pallet-asset-conversion`
+// runs on chain, so results can be predicted before submitting.
+
+// What `add_liquidity` is expected to take from each side and mint in LP tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LiquidityPreview {
+    pub amount1: u128,
+    pub amount2: u128,
+    pub lp_minted: u128,
+}
+
+// Mirrors `Pallet::do_add_liquidity`: on an empty pool both desired amounts
+// are taken and `sqrt(amount1 * amount2) - mint_min_liquidity` LP tokens are
+// minted (the minimum is locked in the pool account). Otherwise the desired
+// amounts are trimmed to the pool's current ratio and LP tokens are minted
+// proportionally to the share of the reserves being added.
+//
+// Returns `None` when the pallet would reject the provision or the math
+// overflows.
+pub fn preview_add_liquidity(
+    reserve1: u128,
+    reserve2: u128,
+    lp_supply: u128,
+    amount1_desired: u128,
+    amount2_desired: u128,
+    mint_min_liquidity: u128,
+) -> Option<LiquidityPreview> {
+    let (amount1, amount2) = if reserve1 == 0 || reserve2 == 0 {
+        (amount1_desired, amount2_desired)
+    } else {
+        let amount2_optimal = quote(amount1_desired, reserve1, reserve2)?;
+        if amount2_optimal <= amount2_desired {
+            (amount1_desired, amount2_optimal)
+        } else {
+            let amount1_optimal = quote(amount2_desired, reserve2, reserve1)?;
+            if amount1_optimal > amount1_desired {
+                return None;
+            }
+            (amount1_optimal, amount2_desired)
+        }
+    };
+
+    let lp_minted = if lp_supply == 0 {
+        amount1.checked_mul(amount2)?.isqrt().checked_sub(mint_min_liquidity)?
+    } else {
+        let side1 = quote(amount1, reserve1, lp_supply)?;
+        let side2 = quote(amount2, reserve2, lp_supply)?;
+        side1.min(side2)
+    };
+
+    Some(LiquidityPreview {
+        amount1,
+        amount2,
+        lp_minted,
+    })
+}
+
+// `amount * reserve_out / reserve_in`, the pallet's `quote`
+pub fn quote(amount: u128, reserve_in: u128, reserve_out: u128) -> Option<u128> {
+    amount.checked_mul(reserve_out)?.checked_div(reserve_in)
+}
+
+// Mirrors `Pallet::get_amount_out`: what a swap of `amount_in` gets out of a
+// pool with the given reserves, after the LP fee (in per mille) is taken from
+// the input. Returns `None` on an empty pool or when the math overflows.
+pub fn get_amount_out(amount_in: u128, reserve_in: u128, reserve_out: u128, lp_fee: u32) -> Option<u128> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+
+    let amount_in_with_fee = amount_in.checked_mul(1000u128.checked_sub(lp_fee.into())?)?;
+    let numerator = amount_in_with_fee.checked_mul(reserve_out)?;
+    let denominator = reserve_in.checked_mul(1000)?.checked_add(amount_in_with_fee)?;
+
+    numerator.checked_div(denominator)
+}
+
+// Mirrors `Pallet::get_amount_in`: what has to go into a pool with the given
+// reserves for a swap to get exactly `amount_out`, LP fee included. Returns
+// `None` when the pool can't pay `amount_out` or the math overflows.
+pub fn get_amount_in(amount_out: u128, reserve_in: u128, reserve_out: u128, lp_fee: u32) -> Option<u128> {
+    if reserve_in == 0 || reserve_out <= amount_out {
+        return None;
+    }
+
+    let numerator = reserve_in.checked_mul(amount_out)?.checked_mul(1000)?;
+    let denominator = (reserve_out - amount_out).checked_mul(1000u128.checked_sub(lp_fee.into())?)?;
+
+    numerator.checked_div(denominator)?.checked_add(1)
+}
+
+// Instantaneous (mid) price of the input token in units of the output token,
+// `reserve_out / reserve_in` with both sides scaled by their decimals. Unlike
+// a swap quote it ignores the LP fee and the price impact of the swap itself.
+// Returns `None` when either reserve is empty.
+pub fn spot_price(reserve_in: u128, decimals_in: u8, reserve_out: u128, decimals_out: u8) -> Option<f64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return None;
+    }
+
+    let amount_in = reserve_in as f64 / 10f64.powi(decimals_in.into());
+    let amount_out = reserve_out as f64 / 10f64.powi(decimals_out.into());
+
+    Some(amount_out / amount_in)
+}
+
+// `a * b / c` rounded down, computed on the full 256-bit product so the
+// intermediate multiplication never overflows. Returns `None` when `c` is
+// zero or the result doesn't fit a `u128`.
+pub fn mul_div(a: u128, b: u128, c: u128) -> Option<u128> {
+    if c == 0 {
+        return None;
+    }
+    if let Some(product) = a.checked_mul(b) {
+        return Some(product / c);
+    }
+
+    // Schoolbook product on 64-bit limbs into `hi * 2^128 + lo`
+    const MASK: u128 = u64::MAX as u128;
+    let (a1, a0, b1, b0) = (a >> 64, a & MASK, b >> 64, b & MASK);
+    let low = a0 * b0;
+    let mid = (low >> 64) + ((a1 * b0) & MASK) + ((a0 * b1) & MASK);
+    let lo = (mid << 64) | (low & MASK);
+    let hi = a1 * b1 + ((a1 * b0) >> 64) + ((a0 * b1) >> 64) + (mid >> 64);
+    if hi >= c {
+        return None;
+    }
+
+    // Long division, one bit of `lo` at a time, keeping the remainder below `c`
+    let (mut rem, mut quotient) = (hi, 0u128);
+    for i in (0..128).rev() {
+        let carry = rem >> 127;
+        rem = (rem << 1) | ((lo >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || rem >= c {
+            rem = rem.wrapping_sub(c);
+            quotient |= 1;
+        }
+    }
+
+    Some(quotient)
+}
+
+// How much worse the price realized by a swap is than the quoted one, in
+// basis points of the quoted price, where the price is the output received per
+// unit of input. Negative when the swap did better than quoted. The realized
+// output is first scaled to the quoted input so that both sides stay amounts
+// of the output token. Returns `None` on empty amounts or overflow.
+pub fn slippage_bps(quoted_in: u128, quoted_out: u128, actual_in: u128, actual_out: u128) -> Option<i128> {
+    if quoted_in == 0 || quoted_out == 0 {
+        return None;
+    }
+
+    let scaled_out = mul_div(actual_out, quoted_in, actual_in)?;
+    let bps = if scaled_out <= quoted_out {
+        i128::try_from(mul_div(quoted_out - scaled_out, 10_000, quoted_out)?).ok()?
+    } else {
+        -i128::try_from(mul_div(scaled_out - quoted_out, 10_000, quoted_out)?).ok()?
+    };
+
+    Some(bps)
+}
+
+// Smallest reserves for which a swap of `amount` plancks of the first token
+// moves the price by at most `slippage` per mille and still gets at least one
+// unit of the second token out, while minting more than `mint_min_liquidity`
+// LP tokens on an empty pool. The first reserve is at least `min_reserve1`,
+// e.g. the existential deposit of the pool account. Returns `None` when the
+// slippage isn't between 1 and 999 per mille or the math overflows.
+pub fn minimal_reserves(
+    amount: u128,
+    slippage: u128,
+    min_reserve1: u128,
+    lp_fee: u32,
+    mint_min_liquidity: u128,
+) -> Option<(u128, u128)> {
+    if !(1..1000).contains(&slippage) {
+        return None;
+    }
+
+    let reserve1 = amount.checked_mul(1000)?.div_ceil(slippage).max(min_reserve1);
+
+    // From `get_amount_out`, the smallest second reserve giving one unit out
+    let amount_with_fee = amount.checked_mul(1000u128.checked_sub(lp_fee.into())?)?;
+    let mut reserve2 = reserve1
+        .checked_mul(1000)?
+        .checked_add(amount_with_fee)?
+        .checked_div(amount_with_fee)?
+        .checked_add(1)?;
+
+    // `sqrt(reserve1 * reserve2)` has to exceed the locked LP minimum
+    let lp_needed = mint_min_liquidity.checked_add(1)?.checked_pow(2)?;
+    if reserve1.checked_mul(reserve2)? < lp_needed {
+        reserve2 = lp_needed.div_ceil(reserve1);
+    }
+
+    Some((reserve1, reserve2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Amounts near `u128::MAX` must not overflow the price comparison
+    #[test]
+    fn swap_slippage() {
+        assert_eq!(slippage_bps(1_000, 2_000, 1_000, 2_000), Some(0));
+        assert_eq!(slippage_bps(1_000, 2_000, 1_000, 1_980), Some(100));
+        assert_eq!(slippage_bps(1_000, 2_000, 990, 2_000), Some(-100));
+        assert_eq!(slippage_bps(0, 2_000, 1_000, 2_000), None);
+
+        let big = u128::MAX / 400 * 200;
+        assert_eq!(slippage_bps(big, big, big, big - big / 200), Some(50));
+        assert_eq!(mul_div(u128::MAX, 10, 20), Some(u128::MAX / 2));
+        assert_eq!(mul_div(u128::MAX, 2, 1), None);
+        assert_eq!(mul_div(u128::MAX, u128::MAX - 1, u128::MAX), Some(u128::MAX - 1));
+    }
+}
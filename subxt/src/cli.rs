@@ -0,0 +1,973 @@
+use std::{collections::HashSet, ffi::OsString, fmt, str::FromStr};
+
+use clap::{parser::ValueSource, ArgAction, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum};
+use subxt::utils::{AccountId32, H256};
+use subxt_signer::sr25519::Keypair;
+
+use crate::{
+    error::AppError, local_asset_location, native_location, query::Query, MortalityConfig, signer_from_secret_uri, MultiLocation, ASSET_ID, NAME, SYMBOL,
+    URI,
+};
+
+// Command line options for the example. Everything is optional so that a plain
+// `cargo run` keeps running the whole flow against the local zombienet. They
+// can also come from the environment or a config file, see `resolve_settings`.
+#[derive(Parser, Debug)]
+#[command(about = "Pay transaction fees with a custom asset through the Asset Conversion pallet")]
+pub struct Cli {
+    /// Node to connect to. Can be given several times to compare the
+    /// read-only state (quote, reserves, balances) across endpoints; anything
+    /// that changes state is only sent to the first one.
+    #[arg(long = "url", value_name = "URL", default_value = URI)]
+    pub urls: Vec<String>,
+
+    /// Connect through an embedded light client following the chain of this
+    /// chain spec (JSON) instead of a node's RPC.
+    #[cfg(feature = "light-client")]
+    #[arg(long, value_name = "PATH", conflicts_with = "urls")]
+    pub chain_spec: Option<String>,
+
+    /// Chain spec of the relay chain `--chain-spec` is a parachain of, like
+    /// Westend for Asset Hub Westend.
+    #[cfg(feature = "light-client")]
+    #[arg(long, value_name = "PATH", requires = "chain_spec")]
+    pub relay_chain_spec: Option<String>,
+
+    /// Work with the existing asset that has this symbol instead of the one
+    /// created by the example.
+    #[arg(long, value_name = "SYMBOL")]
+    pub asset_symbol: Option<String>,
+
+    /// Id of the asset the setup creates.
+    #[arg(long, value_name = "ID", default_value_t = ASSET_ID)]
+    pub asset_id: u32,
+
+    /// Name of the asset the setup creates.
+    #[arg(long, default_value = NAME)]
+    pub name: String,
+
+    /// Symbol of the asset the setup creates.
+    #[arg(long, default_value = SYMBOL)]
+    pub symbol: String,
+
+    /// Decimals of the asset the setup creates.
+    #[arg(long, default_value_t = 0)]
+    pub decimals: u8,
+
+    /// Sign with a Ledger device through this external signing command
+    /// instead of the `//Alice` dev account.
+    #[arg(long, value_name = "COMMAND")]
+    pub ledger_command: Option<String>,
+
+    /// Sign with the account of this secret URI (mnemonic or `0x` seed, with
+    /// optional derivation path) instead of the `//Alice` dev account. Also
+    /// read from the `SURI` environment variable.
+    #[arg(long, value_name = "SURI", conflicts_with = "ledger_command")]
+    pub suri: Option<String>,
+
+    /// Signature scheme of the `--suri` key. Ignored when signing with the
+    /// dev account or a Ledger.
+    #[arg(long, value_enum, default_value_t = KeyType::Sr25519)]
+    pub key_type: KeyType,
+
+    /// Log extra diagnostics, like the predicted vs actual liquidity
+    /// provision of the setup.
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Log as JSON lines instead of human readable text. Logs go to stderr and
+    /// are filtered with `RUST_LOG` (e.g. `info` or `asset_conversion_example=debug`).
+    #[arg(long)]
+    pub log_json: bool,
+
+    /// Print results as JSON instead of human readable text.
+    #[arg(long, conflicts_with = "plain")]
+    pub json: bool,
+
+    /// Print tables (status, price curve) as tab separated values.
+    #[arg(long)]
+    pub plain: bool,
+
+    /// Validate the transactions with the runtime instead of submitting
+    /// them: the setup batch, printed as a call tree, the transfer and the
+    /// swaps, as well as those of the subcommands.
+    #[arg(long, conflicts_with = "confirm")]
+    pub dry_run: bool,
+
+    /// Print the setup batch and ask for confirmation before signing it.
+    #[arg(long)]
+    pub confirm: bool,
+
+    /// Secret URI (e.g. `//Ferdie`) of an account that sends the signer the
+    /// native tokens the setup needs before it runs.
+    #[arg(long, value_name = "SURI", value_parser = parse_suri)]
+    pub funder: Option<Keypair>,
+
+    /// Add the native pool's liquidity in a transaction of its own, after the
+    /// rest of the setup, with its minimums set from the pool's reserves at
+    /// that point and retried when the price moves.
+    #[arg(long)]
+    pub staged_setup: bool,
+
+    /// How the setup batch handles a failing call: `all` reverts the whole
+    /// batch, `best-effort` keeps the calls that went through and reports
+    /// the ones that failed.
+    #[arg(long, value_enum, default_value_t = BatchMode::All)]
+    pub batch_mode: BatchMode,
+
+    /// Build the calls of the `setup`, `transfer` and `quote` subcommands
+    /// from the metadata the node serves instead of the compiled one, for
+    /// runtimes the example wasn't regenerated against.
+    #[arg(long)]
+    pub dynamic: bool,
+
+    /// How many times the staged liquidity provision is retried when the
+    /// pool's price moves before it's included.
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub liquidity_retries: u32,
+
+    /// Seed the pool with the least liquidity that lets it quote the fee of
+    /// the example transfer, plus a small margin, instead of the fixed amounts.
+    #[arg(long)]
+    pub minimal_liquidity: bool,
+
+    /// Minimum balance of the asset the setup creates, in its smallest unit.
+    #[arg(long, value_name = "AMOUNT", default_value_t = SetupParams::DEFAULT.min_balance)]
+    pub min_balance: u128,
+
+    /// Amount of the asset the setup mints to the signer, in its smallest
+    /// unit. Has to cover `--liquidity-asset`.
+    #[arg(long, value_name = "AMOUNT", default_value_t = SetupParams::DEFAULT.mint_amount)]
+    pub mint_amount: u128,
+
+    /// Native plancks the setup adds to the pool, unless `--minimal-liquidity`.
+    #[arg(long, value_name = "AMOUNT", default_value_t = SetupParams::DEFAULT.liquidity_native)]
+    pub liquidity_native: u128,
+
+    /// Amount of the asset the setup adds to the pool, in its smallest unit,
+    /// unless `--minimal-liquidity`.
+    #[arg(long, value_name = "AMOUNT", default_value_t = SetupParams::DEFAULT.liquidity_asset)]
+    pub liquidity_asset: u128,
+
+    /// Another asset for the setup to create and mint, as
+    /// `ID:NAME:SYMBOL:DECIMALS:MINT_AMOUNT`, created with `--min-balance`
+    /// and with a pool between it and `--asset-id` seeded with
+    /// `--liquidity-asset` of each. The run ends with
+    /// a swap from `--asset-id` to the first one. Can be given several times.
+    #[arg(long = "extra-asset", value_name = "SPEC", value_parser = parse_asset_spec)]
+    pub extra_assets: Vec<AssetSpec>,
+
+    /// How far the liquidity changes may go below the desired amounts, and
+    /// the swaps from their quote, in basis points (50 is 0.5%).
+    #[arg(long, value_name = "BPS", default_value_t = 50, value_parser = clap::value_parser!(u16).range(..=10_000))]
+    pub slippage_bps: u16,
+
+    /// Warn when a swap gets a price this many basis points worse than
+    /// quoted before submitting it (100 is 1%).
+    #[arg(long, value_name = "BPS", default_value_t = 100, value_parser = clap::value_parser!(u16).range(..=10_000))]
+    pub slippage_warn_bps: u16,
+
+    /// When the setup fails halfway, destroy the asset it created so the
+    /// chain is left clean.
+    #[arg(long)]
+    pub rollback_on_failure: bool,
+
+    /// Sign against this genesis hash instead of the one reported by the node,
+    /// e.g. when the node is a fork (chopsticks) of another chain.
+    #[arg(long, value_name = "0x..", value_parser = parse_genesis_hash)]
+    pub genesis_hash: Option<H256>,
+
+    /// Read options from this JSON, or TOML when it ends in `.toml`, file, a
+    /// table keyed by the long flags (see `--print-schema`). Flags and
+    /// environment variables override it.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<String>,
+
+    /// Print a JSON Schema of these options and exit.
+    #[arg(long)]
+    pub print_schema: bool,
+
+    /// Submit even though the node doesn't report a development or local
+    /// chain. Without it anything that changes state is refused there.
+    #[arg(long)]
+    pub yes_i_know: bool,
+
+    /// Abort if the runtime is upgraded to a different spec version while the
+    /// example is running.
+    #[arg(long)]
+    pub pin_spec_version: bool,
+
+    /// Number of entries fetched per request when iterating storage maps,
+    /// like when looking up an asset by its symbol or listing the assets,
+    /// balances and pools.
+    #[arg(long, value_name = "N", default_value_t = 64, value_parser = clap::value_parser!(u32).range(1..))]
+    pub page_size: u32,
+
+    /// Times connecting to the node is retried when it can't be reached,
+    /// e.g. while it's restarting.
+    #[arg(long, value_name = "N", default_value_t = crate::reconnect::MAX_RETRIES)]
+    pub max_retries: u32,
+
+    /// Times a transaction is submitted again when it fails transiently, e.g.
+    /// the transaction pool is full, or isn't finalized in time. Rejected
+    /// transactions aren't retried.
+    #[arg(long, value_name = "N", default_value_t = crate::SUBMIT_RETRIES)]
+    pub max_submit_retries: usize,
+
+    /// Wait before the first connection retry, doubled for each of the next
+    /// ones.
+    #[arg(long, value_name = "MS", default_value_t = crate::reconnect::RETRY_BACKOFF.as_millis() as u64)]
+    pub retry_backoff_ms: u64,
+
+    /// Account (SS58 address) that becomes the asset's issuer at the end of
+    /// the setup, instead of the signer.
+    #[arg(long, value_name = "SS58", value_parser = parse_account)]
+    pub issuer: Option<AccountId32>,
+
+    /// Account (SS58 address) that becomes the asset's admin at the end of
+    /// the setup, instead of the signer.
+    #[arg(long, value_name = "SS58", value_parser = parse_account)]
+    pub admin: Option<AccountId32>,
+
+    /// Account (SS58 address) that becomes the asset's freezer at the end of
+    /// the setup, instead of the signer.
+    #[arg(long, value_name = "SS58", value_parser = parse_account)]
+    pub freezer: Option<AccountId32>,
+
+    /// Tip the transactions according to how fast they should be included,
+    /// based on the tips paid in recent blocks. Without it nothing is tipped.
+    #[arg(long, value_enum)]
+    pub priority: Option<Priority>,
+
+    /// Smallest amount a transfer or swap may move. Defaults to 1 so that zero
+    /// amounts, which only burn a fee, are rejected; 0 allows them.
+    #[arg(long, value_name = "AMOUNT", default_value_t = 1)]
+    pub min_amount: u128,
+
+    /// Margin, in percent, added on top of the transfer's fee estimated in the
+    /// asset.
+    #[arg(long, value_name = "PERCENT", default_value_t = crate::FEE_BUFFER_PERCENT)]
+    pub fee_buffer_percent: u8,
+
+    /// Pay the transfer's fee in the native token, with a warning, when the
+    /// asset's pool doesn't hold enough of it to swap the fee.
+    #[arg(long)]
+    pub auto_fallback: bool,
+
+    /// Sign the transactions as mortal, valid for this many blocks after the
+    /// latest one instead of forever. The runtime only supports powers of two
+    /// from 4 to 65536, other values are rounded up. Immortal by default.
+    #[arg(long, value_name = "BLOCKS", value_parser = parse_mortal_period)]
+    pub mortal_period: Option<MortalityConfig>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+// Single operations, run on their own instead of the whole example. Without
+// one the example runs the setup, then the transfer paying its fee in the
+// asset, as before. It's parsed once, so the size of the locations `Quote`
+// holds doesn't matter.
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+pub enum Command {
+    /// Create the asset and its pool with the native token when missing and
+    /// add the liquidity, without the example transfer
+    Setup,
+    /// Swap an exact amount of the first asset of a path for the last one,
+    /// or with --exact-out the first one for an exact amount of the last one
+    Swap {
+        /// Amount of the first asset of the path, or of the last one with
+        /// --exact-out, in its smallest unit
+        amount: u128,
+        /// Comma separated path of assets, `native` or asset ids. Defaults
+        /// to the native token then `--asset-id`.
+        #[arg(long, value_name = "ASSETS", value_delimiter = ',', value_parser = parse_path_asset)]
+        path: Vec<MultiLocation>,
+        /// Least amount of the last asset accepted. Defaults to the quote
+        /// less `--slippage-bps`.
+        #[arg(long, value_name = "AMOUNT", conflicts_with = "exact_out")]
+        min_out: Option<u128>,
+        /// Get exactly `amount` of the last asset, spending up to the quote
+        /// plus `--slippage-bps` of the first one
+        #[arg(long)]
+        exact_out: bool,
+    },
+    /// Transfer native plancks, keeping the signer alive
+    Transfer {
+        /// Account (SS58 address) receiving the transfer
+        #[arg(value_parser = parse_account)]
+        dest: AccountId32,
+        /// Native plancks to transfer
+        amount: u128,
+        /// Pay the fee in `--asset-id` instead of the native token
+        #[arg(long)]
+        fee_in_asset: bool,
+    },
+    /// Print what the pool between two assets quotes for an amount
+    Quote {
+        /// Amount of `--from` given, or of `--to` wanted with `--exact-out`
+        amount: u128,
+        /// Asset given, `native` or an asset id. Defaults to `native`.
+        #[arg(long, value_name = "ASSET", value_parser = parse_path_asset)]
+        from: Option<MultiLocation>,
+        /// Asset received, `native` or an asset id. Defaults to `--asset-id`.
+        #[arg(long, value_name = "ASSET", value_parser = parse_path_asset)]
+        to: Option<MultiLocation>,
+        /// Quote how much of `--from` it takes to get `amount` of `--to`
+        #[arg(long)]
+        exact_out: bool,
+    },
+    /// Print the swaps and liquidity changes of the finalized blocks until
+    /// Ctrl-C
+    Watch,
+    /// Print the node's runtime, the finalized block, the asset's metadata
+    /// and the signer's balance of it, then whether the chain is ready for
+    /// the example (asset, pool, liquidity and fee estimate)
+    Status,
+    /// Add liquidity to or remove it from the native/asset pool
+    Liquidity {
+        #[command(subcommand)]
+        action: LiquidityAction,
+    },
+    /// Asset admin operations, signed by the asset's owner, admin or freezer
+    Admin {
+        #[command(subcommand)]
+        action: AdminAction,
+    },
+    /// Print every asset with metadata: its id, symbol, decimals and name,
+    /// one per line as they're fetched
+    List,
+    /// Print the accounts holding `--asset-id`, their balance and status, one
+    /// per line as they're fetched
+    Balances {
+        /// List the assets this account (SS58 address) holds instead
+        #[arg(long, value_name = "SS58", value_parser = parse_account)]
+        account: Option<AccountId32>,
+    },
+    /// Print every pool: its pair of assets and its LP token, one per line
+    /// as they're fetched
+    ListPools,
+    /// Only print the fee of the example transfer in the custom asset,
+    /// without submitting anything unless the pool is missing
+    Estimate,
+    /// Print how the fee of the example transfer is made up (base, length
+    /// and weight fees, tip)
+    FeeBreakdown,
+    /// Quote swapping native tokens for the asset at geometrically growing
+    /// amounts and print the price curve
+    PriceCurve {
+        /// First amount quoted, in native plancks, each next one ten times
+        /// the previous
+        #[arg(default_value_t = crate::QUOTE_AMOUNT)]
+        amount: u128,
+    },
+    /// Quote a swap along a path of assets hop by hop and compare it with the
+    /// runtime's quote between its ends
+    QuotePath {
+        /// Comma separated path of assets, `native` or asset ids (e.g.
+        /// `native,1,2`)
+        #[arg(value_name = "ASSETS", value_delimiter = ',', value_parser = parse_path_asset, required = true)]
+        path: Vec<MultiLocation>,
+        /// Amount quoted, in the smallest unit of the first asset of the path
+        #[arg(long, default_value_t = crate::QUOTE_AMOUNT)]
+        amount: u128,
+    },
+    /// Re-submit the batches recorded in a run log, signed by the current
+    /// signer
+    Replay {
+        /// JSON file listing the batches as hex encoded calls, see `replay`
+        file: String,
+    },
+    /// Mint `--asset-id` to these accounts in a single batch
+    MintTo {
+        #[arg(value_name = "ADDRESS:AMOUNT", value_parser = parse_mint, required = true)]
+        mints: Vec<(AccountId32, u128)>,
+        /// Most mints put in the batch, to keep the extrinsic well within a
+        /// block
+        #[arg(long, value_name = "N", default_value_t = crate::MAX_BATCH_LEN)]
+        max_batch_len: usize,
+    },
+    /// Print the signer's address, native balance and roles on the asset,
+    /// without submitting anything
+    Whoami,
+    /// Answer read-only queries, without signing anything
+    Query {
+        /// `reserves:<asset id>`, `balance:<SS58>`, `asset-balance:<asset
+        /// id>:<SS58>` or `quote:<amount>:<asset id>`
+        #[arg(value_name = "QUERY", required = true)]
+        queries: Vec<Query>,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum LiquidityAction {
+    /// Add up to these amounts, accepting down to `--slippage-bps` below what
+    /// the pool takes at its current price
+    Add {
+        /// Native plancks to add at most
+        native: u128,
+        /// Amount of `--asset-id` to add at most, in its smallest unit
+        asset: u128,
+    },
+    /// Burn this many of the signer's LP tokens, accepting down to
+    /// `--slippage-bps` below their share of the reserves
+    Remove {
+        /// LP tokens to burn
+        lp_amount: u128,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum AdminAction {
+    /// Freeze the asset, or only the account's balance of it with --account
+    Freeze {
+        asset_id: u32,
+        /// Account (SS58 address) to freeze instead of the whole asset
+        #[arg(long, value_name = "SS58", value_parser = parse_account)]
+        account: Option<AccountId32>,
+    },
+    /// Thaw the asset, or only the account's balance of it with --account
+    Thaw {
+        asset_id: u32,
+        /// Account (SS58 address) to thaw instead of the whole asset
+        #[arg(long, value_name = "SS58", value_parser = parse_account)]
+        account: Option<AccountId32>,
+    },
+    /// Change the asset's minimum balance
+    SetMinBalance { asset_id: u32, min_balance: u128 },
+    /// Clear the asset's metadata, returning its deposit to the owner
+    ClearMetadata { asset_id: u32 },
+    /// Burn an amount of the asset, signed by its admin
+    Burn {
+        asset_id: u32,
+        /// Amount to burn, in the asset's smallest unit
+        amount: u128,
+        /// Account (SS58 address) whose tokens are burned. Defaults to the
+        /// signer.
+        #[arg(long, value_name = "SS58", value_parser = parse_account)]
+        from: Option<AccountId32>,
+    },
+    /// Hand the asset over to another account, signed by its owner
+    TransferOwnership {
+        asset_id: u32,
+        /// Account (SS58 address) becoming the asset's owner
+        #[arg(value_parser = parse_account)]
+        new_owner: AccountId32,
+    },
+}
+
+// The asset the setup creates and describes in its metadata
+#[derive(Debug, Clone)]
+pub struct AssetConfig {
+    pub id: u32,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl AssetConfig {
+    // Rejects the metadata `set_metadata` would fail on, given the
+    // `StringLimit` of `pallet-assets`, before anything is signed
+    pub fn validate(&self, string_limit: u32) -> Result<(), String> {
+        if self.symbol.is_empty() {
+            return Err("The asset symbol can't be empty, set one with --symbol".into());
+        }
+        for (option, value) in [("name", &self.name), ("symbol", &self.symbol)] {
+            if value.len() > string_limit as usize {
+                return Err(format!(
+                    "The asset {option} `{value}` is {} bytes long but the chain accepts at most {string_limit}, pick a shorter one with --{option}",
+                    value.len()
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+// An asset to create, along with what's minted to its creator, e.g. one the
+// setup creates next to the one of `AssetConfig`, see `--extra-asset`
+#[derive(Debug, Clone)]
+pub struct AssetSpec {
+    pub id: u32,
+    pub name: String,
+    pub symbol: String,
+    pub decimals: u8,
+    pub min_balance: u128,
+    pub mint_amount: u128,
+}
+
+impl AssetSpec {
+    // The asset of `config`, created and minted with the amounts of `params`
+    pub fn new(config: &AssetConfig, params: &SetupParams) -> Self {
+        Self {
+            id: config.id,
+            name: config.name.clone(),
+            symbol: config.symbol.clone(),
+            decimals: config.decimals,
+            min_balance: params.min_balance,
+            mint_amount: params.mint_amount,
+        }
+    }
+
+    pub fn config(&self) -> AssetConfig {
+        AssetConfig {
+            id: self.id,
+            name: self.name.clone(),
+            symbol: self.symbol.clone(),
+            decimals: self.decimals,
+        }
+    }
+}
+
+// Amounts the setup creates the asset and seeds the pool with
+#[derive(Clone, Copy, Debug)]
+pub struct SetupParams {
+    pub min_balance: u128,
+    pub mint_amount: u128,
+    pub liquidity_native: u128,
+    pub liquidity_asset: u128,
+}
+
+impl SetupParams {
+    pub const DEFAULT: Self = Self {
+        min_balance: 1,
+        mint_amount: 100_000_000_000_000,
+        liquidity_native: 10_000_000_000,
+        liquidity_asset: 10_000_000,
+    };
+
+    pub fn from_cli(cli: &Cli) -> Self {
+        Self {
+            min_balance: cli.min_balance,
+            mint_amount: cli.mint_amount,
+            liquidity_native: cli.liquidity_native,
+            liquidity_asset: cli.liquidity_asset,
+        }
+    }
+
+    // Rejects amounts the setup batch would fail on, before anything is signed
+    pub fn validate(&self) -> Result<(), AppError> {
+        if self.liquidity_native == 0 || self.liquidity_asset == 0 {
+            return Err(AppError::Setup(
+                "the liquidity amounts can't be zero, set them with --liquidity-native and --liquidity-asset".into(),
+            ));
+        }
+        if self.mint_amount < self.liquidity_asset {
+            return Err(AppError::Setup(format!(
+                "the mint amount {} doesn't cover the {} of the asset added to the pool",
+                self.mint_amount, self.liquidity_asset
+            )));
+        }
+        Ok(())
+    }
+
+    // The other assets have to differ from `asset_id` and each other, and
+    // cover their side of their pool with ours, which is minted enough for
+    // all of its pools
+    pub fn validate_extra_assets(&self, asset_id: u32, specs: &[AssetSpec]) -> Result<(), AppError> {
+        for (index, spec) in specs.iter().enumerate() {
+            if spec.id == asset_id || specs[..index].iter().any(|other| other.id == spec.id) {
+                return Err(AppError::Setup(format!("the asset {} is given more than once", spec.id)));
+            }
+            if spec.mint_amount < self.liquidity_asset {
+                return Err(AppError::Setup(format!(
+                    "the mint amount {} of the asset {} doesn't cover the {} added to its pool",
+                    spec.mint_amount, spec.id, self.liquidity_asset
+                )));
+            }
+        }
+
+        let needed = self.liquidity_asset.saturating_mul(specs.len() as u128 + 1);
+        if self.mint_amount < needed {
+            return Err(AppError::Setup(format!(
+                "the mint amount {} doesn't cover the {needed} of the asset added to its {} pools",
+                self.mint_amount,
+                specs.len() + 1
+            )));
+        }
+        Ok(())
+    }
+}
+
+impl Default for SetupParams {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+// `utility.batch_all` or `utility.force_batch`
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatchMode {
+    All,
+    BestEffort,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum KeyType {
+    Sr25519,
+    Ed25519,
+    Ecdsa,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+// Prefix of the environment variables setting options, e.g.
+// `ASSET_CONVERSION_ASSET_SYMBOL` for `--asset-symbol`
+const ENV_PREFIX: &str = "ASSET_CONVERSION_";
+
+// Options whose values are never logged
+const SECRET_OPTIONS: [&str; 2] = ["funder", "suri"];
+
+// Where the value of an option came from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    CommandLine,
+    Environment,
+    ConfigFile,
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Source::CommandLine => "command line",
+            Source::Environment => "environment",
+            Source::ConfigFile => "config file",
+            Source::Default => "default",
+        })
+    }
+}
+
+// The value an option ended up with, redacted for secrets, and its source
+#[derive(Clone, Debug)]
+pub struct EffectiveOption {
+    pub long: String,
+    pub value: String,
+    pub source: Source,
+}
+
+// The options of the run, along with the effective value and the source of
+// every one that has a value
+pub struct Settings {
+    pub cli: Cli,
+    pub effective: Vec<EffectiveOption>,
+}
+
+// Loads the options from, by order of precedence, the command line, the
+// environment, the `--config` file and the defaults. The file and the
+// environment are turned into extra arguments for the options the layers
+// above don't set, so each option keeps a single source and everything goes
+// through the same parsing and validation.
+pub fn resolve_settings() -> Result<Settings, AppError> {
+    let args: Vec<OsString> = std::env::args_os().collect();
+    let command = Cli::command();
+    let matches = command.clone().try_get_matches_from(&args)?;
+
+    let from_cli: HashSet<String> = command
+        .get_arguments()
+        .filter(|arg| matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine))
+        .filter_map(|arg| arg.get_long().map(str::to_string))
+        .collect();
+
+    let mut from_env = Vec::new();
+    let mut env_set = HashSet::new();
+    for long in command.get_arguments().filter_map(|arg| arg.get_long()) {
+        if from_cli.contains(long) {
+            continue;
+        }
+        let Ok(value) = std::env::var(format!("{ENV_PREFIX}{}", long.to_uppercase().replace('-', "_"))) else {
+            continue;
+        };
+        if push_option(&command, &mut from_env, long, &serde_json::Value::String(value)) {
+            env_set.insert(long.to_string());
+        }
+    }
+
+    let config = matches
+        .get_one::<String>("config")
+        .cloned()
+        .or_else(|| std::env::var(format!("{ENV_PREFIX}CONFIG")).ok());
+    let mut from_file = Vec::new();
+    let mut file_set = HashSet::new();
+    if let Some(path) = &config {
+        let options = read_config(path)?;
+        for (long, value) in &options {
+            if command.get_arguments().all(|arg| arg.get_long() != Some(long.as_str())) {
+                return Err(AppError::File { path: path.clone(), reason: format!("unknown option `{long}`") });
+            }
+            if from_cli.contains(long) || env_set.contains(long) {
+                continue;
+            }
+            if push_option(&command, &mut from_file, long, value) {
+                file_set.insert(long.to_string());
+            }
+        }
+    }
+
+    let mut merged = vec![args[0].clone()];
+    merged.extend(from_file);
+    merged.extend(from_env);
+    merged.extend(args.into_iter().skip(1));
+    let matches = command.clone().try_get_matches_from(merged)?;
+
+    let mut effective = Vec::new();
+    for arg in command.get_arguments() {
+        let (Some(long), Some(values)) = (arg.get_long(), matches.get_raw(arg.get_id().as_str())) else {
+            continue;
+        };
+        let source = if from_cli.contains(long) {
+            Source::CommandLine
+        } else if env_set.contains(long) {
+            Source::Environment
+        } else if file_set.contains(long) {
+            Source::ConfigFile
+        } else {
+            Source::Default
+        };
+        let value = if SECRET_OPTIONS.contains(&long) {
+            "<redacted>".to_string()
+        } else {
+            values.map(|value| value.to_string_lossy()).collect::<Vec<_>>().join(", ")
+        };
+        effective.push(EffectiveOption { long: long.to_string(), value, source });
+    }
+
+    Ok(Settings { cli: Cli::from_arg_matches(&matches)?, effective })
+}
+
+// Turns an option's value from the environment or the config file into
+// arguments. Switches are only passed when true. Returns whether the option
+// was set.
+pub(crate) fn push_option(command: &clap::Command, args: &mut Vec<OsString>, long: &str, value: &serde_json::Value) -> bool {
+    let is_switch = command
+        .get_arguments()
+        .any(|arg| arg.get_long() == Some(long) && matches!(arg.get_action(), ArgAction::SetTrue));
+
+    let values = match value {
+        serde_json::Value::Array(values) => values.clone(),
+        value => vec![value.clone()],
+    };
+
+    let mut set = false;
+    for value in values {
+        let value = match value {
+            serde_json::Value::String(value) => value,
+            value => value.to_string(),
+        };
+        if is_switch {
+            if matches!(value.as_str(), "true" | "1") {
+                args.push(format!("--{long}").into());
+                set = true;
+            }
+        } else {
+            args.push(format!("--{long}").into());
+            args.push(value.into());
+            set = true;
+        }
+    }
+
+    set
+}
+
+// The config file's options, TOML when its name ends in `.toml` and JSON
+// otherwise. Either way they're read into the same map, TOML's dates aside
+// which no option takes.
+pub(crate) fn read_config(path: &str) -> Result<serde_json::Map<String, serde_json::Value>, AppError> {
+    let file_error = |reason: String| AppError::File { path: path.to_string(), reason };
+    let contents = std::fs::read_to_string(path).map_err(|e| file_error(e.to_string()))?;
+    if path.ends_with(".toml") {
+        toml::from_str(&contents).map_err(|e| file_error(e.to_string()))
+    } else {
+        serde_json::from_str(&contents).map_err(|e| file_error(e.to_string()))
+    }
+}
+
+// JSON Schema of the options above, built from the parser's own definition so
+// it can't drift from it. Every option is a property named after its long flag.
+// Values are validated by the parsers, so the schema only tells switches
+// (booleans), repeatable options (arrays) and values (strings) apart.
+pub fn schema() -> serde_json::Value {
+    let command = Cli::command();
+
+    let mut properties = serde_json::Map::new();
+    for arg in command.get_arguments() {
+        let Some(name) = arg.get_long() else {
+            continue;
+        };
+
+        let defaults: Vec<_> = arg
+            .get_default_values()
+            .iter()
+            .map(|default| default.to_string_lossy().into_owned())
+            .collect();
+
+        let mut property = match arg.get_action() {
+            ArgAction::Help | ArgAction::HelpShort | ArgAction::HelpLong | ArgAction::Version => continue,
+            ArgAction::SetTrue => serde_json::json!({ "type": "boolean", "default": false }),
+            ArgAction::Append => serde_json::json!({
+                "type": "array",
+                "items": { "type": "string" },
+                "default": defaults,
+            }),
+            _ => match defaults.as_slice() {
+                [default] => serde_json::json!({ "type": "string", "default": default }),
+                _ => serde_json::json!({ "type": "string" }),
+            },
+        };
+        if let Some(help) = arg.get_help() {
+            property["description"] = help.to_string().into();
+        }
+
+        properties.insert(name.to_string(), property);
+    }
+
+    serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": command.get_name(),
+        "description": command.get_about().map(|about| about.to_string()),
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    })
+}
+
+// Genesis hashes are 32 bytes, hex encoded with an optional `0x` prefix
+fn parse_genesis_hash(value: &str) -> Result<H256, String> {
+    let bytes = hex::decode(value.strip_prefix("0x").unwrap_or(value))
+        .map_err(|e| format!("invalid hex: {e}"))?;
+
+    if bytes.len() != 32 {
+        return Err(format!("expected 32 bytes, got {}", bytes.len()));
+    }
+
+    Ok(H256::from_slice(&bytes))
+}
+
+// Path assets are either `native` or the id of an asset of `pallet-assets`
+fn parse_path_asset(value: &str) -> Result<MultiLocation, String> {
+    if value.eq_ignore_ascii_case("native") {
+        return Ok(native_location());
+    }
+
+    let asset_id = value
+        .parse()
+        .map_err(|_| format!("expected `native` or an asset id, got {value}"))?;
+
+    Ok(local_asset_location(asset_id))
+}
+
+fn parse_asset_spec(value: &str) -> Result<AssetSpec, String> {
+    let [id, name, symbol, decimals, mint_amount] = value.split(':').collect::<Vec<_>>()[..] else {
+        return Err(format!("expected ID:NAME:SYMBOL:DECIMALS:MINT_AMOUNT, got {value}"));
+    };
+
+    Ok(AssetSpec {
+        id: id.parse().map_err(|_| format!("expected an asset id, got {id}"))?,
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        decimals: decimals.parse().map_err(|_| format!("expected a number of decimals, got {decimals}"))?,
+        // Set from `--min-balance` once it's parsed too
+        min_balance: SetupParams::DEFAULT.min_balance,
+        mint_amount: mint_amount.parse().map_err(|_| format!("expected an amount to mint, got {mint_amount}"))?,
+    })
+}
+
+// `ADDRESS:AMOUNT`, the amount in the asset's smallest unit
+fn parse_mint(value: &str) -> Result<(AccountId32, u128), String> {
+    let Some((account, amount)) = value.rsplit_once(':') else {
+        return Err(format!("expected ADDRESS:AMOUNT, got {value}"));
+    };
+
+    Ok((
+        parse_account(account)?,
+        amount.parse().map_err(|_| format!("expected an amount to mint, got {amount}"))?,
+    ))
+}
+
+fn parse_mortal_period(value: &str) -> Result<MortalityConfig, String> {
+    let period = value.parse().map_err(|_| format!("expected a number of blocks, got {value}"))?;
+    MortalityConfig::new(period)
+}
+
+fn parse_account(value: &str) -> Result<AccountId32, String> {
+    AccountId32::from_str(value).map_err(|e| format!("invalid SS58 address: {e}"))
+}
+
+fn parse_suri(value: &str) -> Result<Keypair, String> {
+    signer_from_secret_uri(value).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_BATCH_LEN, QUOTE_AMOUNT};
+
+    // The example config file round-trips through TOML, and each of its
+    // options is a flag whose value parses
+    #[test]
+    fn toml_config_roundtrip() {
+        use clap::{CommandFactory, Parser};
+
+        let options = read_config("./tests/fixtures/config.toml").unwrap();
+        let reparsed: serde_json::Map<String, serde_json::Value> =
+            toml::from_str(&toml::to_string(&options).unwrap()).unwrap();
+        assert_eq!(reparsed, options);
+
+        let command = Cli::command();
+        let mut args = vec!["asset-conversion-example".into()];
+        for (long, value) in &options {
+            assert!(push_option(&command, &mut args, long, value), "{long}");
+        }
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert_eq!(cli.asset_id, 1984);
+        assert_eq!(cli.mint_amount, 100_000_000_000_000_000_000);
+        assert_eq!(cli.slippage_bps, 100);
+        assert!(cli.staged_setup);
+    }
+
+    #[test]
+    fn one_shot_subcommands() {
+        use clap::Parser;
+
+        let cli = Cli::try_parse_from(["asset-conversion-example", "quote-path", "native,1,2"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::QuotePath { path, amount: QUOTE_AMOUNT }) if path.len() == 3));
+
+        let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        let args = ["asset-conversion-example", "mint-to", &format!("{bob}:10"), &format!("{bob}:20")];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(cli.command, Some(Command::MintTo { mints, max_batch_len: MAX_BATCH_LEN }) if mints.len() == 2));
+        assert!(Cli::try_parse_from(["asset-conversion-example", "mint-to"]).is_err());
+    }
+
+    #[test]
+    fn query_subcommand() {
+        use clap::Parser;
+
+        let bob = "5FHneW46xGXgs5mUiveU4sbTyGBzmstUspZC92UhjJM694ty";
+        let args = ["asset-conversion-example", "query", "reserves:1984", &format!("balance:{bob}")];
+        let cli = Cli::try_parse_from(args).unwrap();
+        assert!(matches!(cli.command, Some(Command::Query { queries }) if queries.len() == 2));
+        assert!(Cli::try_parse_from(["asset-conversion-example", "query"]).is_err());
+    }
+
+    // Genesis hashes are exactly 32 bytes of hex, with or without `0x`
+    #[test]
+    fn genesis_hash_flag() {
+        use clap::Parser;
+
+        let hash = "67f9723393ef76214df0118c34bbbd3dbebc8ed46a10973a8c969d48fe7598c9";
+        for value in [hash.to_string(), format!("0x{hash}")] {
+            let cli = Cli::try_parse_from(["asset-conversion-example", "--genesis-hash", &value]).unwrap();
+            assert_eq!(cli.genesis_hash.map(hex::encode).as_deref(), Some(hash));
+        }
+        for value in [&hash[2..], "0x1234", "not hex"] {
+            assert!(Cli::try_parse_from(["asset-conversion-example", "--genesis-hash", value]).is_err(), "{value}");
+        }
+    }
+}
@@ -0,0 +1,715 @@
+use std::{cell::Cell, sync::Mutex, time::Duration};
+
+use subxt::{
+    tx::{Payload, Signer},
+    utils::{AccountId32, MultiAddress, MultiSignature, H256},
+    OnlineClient,
+};
+use subxt_signer::sr25519::Keypair;
+use tracing::{info, warn};
+
+use crate::{
+    asset_balance, asset_exists, check_amount, check_metadata_compatibility, cli::AssetSpec, clear_metadata_call, convert_fees, create_asset_call, create_asset_calls, create_pool_call, create_pool_with_native_call,
+    dry_run, error::AppError,
+    estimate_fee, estimate_fees, local, local_asset_location, mint_calls, native_location, params_builder, pool_exists, pool_info,
+    provide_liquidity_to_token_native_pool_call, remove_liquidity_call, reports_already_exists, set_asset_metadata_call, set_min_balance_call, set_team_call, submit_reconnecting,
+    swap_exact_tokens_for_tokens_call, transfer_asset_call, transfer_ownership_call, with_fee_buffer, BalancesCall, Call, CallPayload, CustomConfig, MortalityConfig, MultiLocation,
+    reconnect::{ReconnectingClient, MAX_RETRIES, RETRY_BACKOFF},
+    signer::create_verified,
+    watch::watch_finalized,
+    SubmissionReceipt, SubmitPolicy, FEE_BUFFER_PERCENT,
+};
+
+// Next nonce of the client's signer. It's read from the chain once and then
+// counted locally, so transactions submitted one after the other don't each
+// depend on the node's view of the account.
+#[derive(Debug, Default)]
+pub struct AccountNonce {
+    next: Option<u64>,
+}
+
+impl AccountNonce {
+    // Hands out the next nonce, or `None` when it has to be read first
+    fn take(&mut self) -> Option<u64> {
+        let nonce = self.next?;
+        self.next = Some(nonce + 1);
+        Some(nonce)
+    }
+}
+
+// Which side of a quote is fixed: the amount given (`ExactIn`) or the amount
+// wanted back (`ExactOut`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteKind {
+    ExactIn,
+    ExactOut,
+}
+
+// Lets a client sign with a signer it doesn't own, e.g. the one the example's
+// flow was given
+pub struct BorrowedSigner<'a, S>(pub &'a S);
+
+impl<S: Signer<CustomConfig>> Signer<CustomConfig> for BorrowedSigner<'_, S> {
+    fn account_id(&self) -> AccountId32 {
+        self.0.account_id()
+    }
+
+    fn address(&self) -> MultiAddress<AccountId32, ()> {
+        self.0.address()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> MultiSignature {
+        self.0.sign(signer_payload)
+    }
+}
+
+// What the example does, as a library: every method builds the same call as
+// the example and submits it on its own, signed by the client's signer and
+// waiting for it to be finalized. Amounts are in the smallest unit of their
+// token. Transactions are immortal and untipped unless `with_mortality` and
+// `with_tip` say otherwise, see also `transfer_with_tip`.
+pub struct AssetHubClient<S = Keypair> {
+    client: ReconnectingClient,
+    signer: S,
+    nonce: Mutex<AccountNonce>,
+    fee_buffer_percent: u8,
+    policy: SubmitPolicy,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    min_amount: u128,
+}
+
+impl<S: Signer<CustomConfig>> AssetHubClient<S> {
+    // A client on an existing connection, which isn't re-established when
+    // it's lost, see `connect` for one that is
+    pub fn new(api: OnlineClient<CustomConfig>, signer: S) -> Self {
+        Self::with_client(ReconnectingClient::from_client(api), signer)
+    }
+
+    fn with_client(client: ReconnectingClient, signer: S) -> Self {
+        Self {
+            client,
+            signer,
+            nonce: Mutex::default(),
+            fee_buffer_percent: FEE_BUFFER_PERCENT,
+            policy: SubmitPolicy::default(),
+            tip: 0,
+            mortality: None,
+            min_amount: 1,
+        }
+    }
+
+    // Margin, in percent, `estimate_fee_in_asset` adds on top of the estimate
+    pub fn with_fee_buffer(mut self, percent: u8) -> Self {
+        self.fee_buffer_percent = percent;
+        self
+    }
+
+    // Retries and refusal of the client's submissions, see `SubmitPolicy`
+    pub fn with_submit_policy(mut self, policy: SubmitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    // Native plancks tipped on every transaction of the client
+    pub fn with_tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    // Era of every transaction of the client, immortal when unset
+    pub fn with_mortality(mut self, mortality: Option<MortalityConfig>) -> Self {
+        self.mortality = mortality;
+        self
+    }
+
+    // Smallest amount the client's transfers may move, see `--min-amount`. It
+    // defaults to 1, refusing transfers of nothing that only burn a fee.
+    pub fn with_min_amount(mut self, min_amount: u128) -> Self {
+        self.min_amount = min_amount;
+        self
+    }
+
+    // Signs against `genesis_hash` instead of the node's, e.g. on a fork,
+    // reconnections included
+    pub fn with_genesis_hash(mut self, genesis_hash: H256) -> Self {
+        self.client = self.client.with_genesis_hash(genesis_hash);
+        self
+    }
+
+    pub async fn from_url(url: &str, signer: S) -> Result<Self, AppError> {
+        Self::connect(url, signer, MAX_RETRIES, RETRY_BACKOFF).await
+    }
+
+    // Connects through an embedded light client following the chain of
+    // `chain_spec`, and its relay chain's for a parachain, see
+    // `light_client::rpc_client`. There's no node to reconnect to, so
+    // operations are only run once.
+    #[cfg(feature = "light-client")]
+    pub async fn from_chain_spec(chain_spec: &str, relay_chain_spec: Option<&str>, signer: S) -> Result<Self, AppError> {
+        let rpc_client = crate::light_client::rpc_client(chain_spec, relay_chain_spec)?;
+        let api = OnlineClient::<CustomConfig>::from_rpc_client(rpc_client).await?;
+        crate::reconnect::check_connection(&api).await?;
+
+        Ok(Self::new(api, signer))
+    }
+
+    // Connects to `url`, and connects again whenever the connection is lost,
+    // retrying what was being done up to `max_retries` times, `backoff`
+    // apart and twice as long each time
+    pub async fn connect(url: &str, signer: S, max_retries: u32, backoff: Duration) -> Result<Self, AppError> {
+        let client = ReconnectingClient::connect(url, max_retries, backoff).await?;
+        Ok(Self::with_client(client, signer))
+    }
+
+    // The client currently connected
+    pub fn api(&self) -> OnlineClient<CustomConfig> {
+        self.client.api()
+    }
+
+    pub fn signer(&self) -> &S {
+        &self.signer
+    }
+
+    // Creates the asset with the signer as its admin
+    pub async fn create_asset(
+        &self,
+        asset_id: u32,
+        min_balance: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = create_asset_call(asset_id, self.signer.address(), min_balance);
+        self.submit(call).await
+    }
+
+    pub async fn set_metadata(
+        &self,
+        asset_id: u32,
+        name: &str,
+        symbol: &str,
+        decimals: u8,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = set_asset_metadata_call(asset_id, name.as_bytes().to_vec(), symbol.as_bytes().to_vec(), decimals);
+        self.submit(call).await
+    }
+
+    // Mints to `beneficiary`, touching its account of the asset first when
+    // it has none, see `mint_calls`
+    pub async fn mint(
+        &self,
+        asset_id: u32,
+        beneficiary: AccountId32,
+        amount: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let calls = self
+            .client
+            .retry(|api| {
+                let beneficiary = beneficiary.clone();
+                async move { mint_calls(&api, asset_id, &beneficiary, amount).await }
+            })
+            .await?;
+        self.submit(batch_of(calls)).await
+    }
+
+    // Hands the asset's issuer, admin and freezer roles over, e.g. to hand off
+    // control of an asset created by a dev account
+    pub async fn set_team(
+        &self,
+        asset_id: u32,
+        issuer: AccountId32,
+        admin: AccountId32,
+        freezer: AccountId32,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = set_team_call(asset_id, issuer.into(), admin.into(), freezer.into());
+        self.submit(call).await
+    }
+
+    // Makes `owner` the asset's owner, which is who gets the deposit back
+    pub async fn transfer_ownership(&self, asset_id: u32, owner: AccountId32) -> Result<SubmissionReceipt, AppError> {
+        let call = transfer_ownership_call(asset_id, owner.into());
+        self.submit(call).await
+    }
+
+    // Changes the asset's minimum balance, see `set_min_balance_call`
+    pub async fn set_min_balance(&self, asset_id: u32, min_balance: u128) -> Result<SubmissionReceipt, AppError> {
+        self.submit(set_min_balance_call(asset_id, min_balance)).await
+    }
+
+    // Clears the asset's metadata, returning its deposit to the owner
+    pub async fn clear_metadata(&self, asset_id: u32) -> Result<SubmissionReceipt, AppError> {
+        self.submit(clear_metadata_call(asset_id)).await
+    }
+
+    // Creates the pool pairing the asset with the native token
+    pub async fn create_pool(
+        &self,
+        asset_id: u32,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = create_pool_with_native_call(asset_id);
+        self.submit(call).await
+    }
+
+    // Creates the asset of `spec` with the signer as its admin, sets its
+    // metadata and mints `mint_amount` of it to the signer, in one
+    // transaction, see `create_asset_calls`, unless the asset already exists. When creating it fails
+    // because it was created in the meantime, e.g. by another run, it's
+    // checked for again and the failure ignored if it's there now. Tells
+    // whether the client created it.
+    pub async fn ensure_asset(&self, spec: &AssetSpec) -> Result<bool, AppError> {
+        if self.asset_exists(spec.id).await? {
+            info!(asset_id = spec.id, "The asset already exists, skipping create, set_metadata and mint");
+            return Ok(false);
+        }
+
+        let calls = create_asset_calls(spec, &self.signer.account_id());
+        match self.submit(batch_of(calls)).await {
+            Ok(_) => Ok(true),
+            Err(e) if reports_already_exists(&e) && self.asset_exists(spec.id).await? => {
+                warn!(asset_id = spec.id, "The asset was created concurrently, leaving it as it is");
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Creates the pool between the two assets, given in the order of its key,
+    // unless it already exists. Like `ensure_asset`, a pool created in the
+    // meantime isn't an error, and it tells whether the client created it.
+    pub async fn ensure_pool(&self, asset1: MultiLocation, asset2: MultiLocation) -> Result<bool, AppError> {
+        if self.pool_exists(asset1.clone(), asset2.clone()).await? {
+            info!(?asset1, ?asset2, "The pool already exists, skipping create_pool");
+            return Ok(false);
+        }
+
+        match self.submit(create_pool_call(asset1.clone(), asset2.clone())).await {
+            Ok(_) => Ok(true),
+            Err(e) if reports_already_exists(&e) && self.pool_exists(asset1.clone(), asset2.clone()).await? => {
+                warn!(?asset1, ?asset2, "The pool was created concurrently, leaving it as it is");
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // Adds liquidity to the native/asset pool, minting the LP tokens to the
+    // signer
+    pub async fn add_liquidity(
+        &self,
+        asset_id: u32,
+        native_desired: u128,
+        asset_desired: u128,
+        native_min: u128,
+        asset_min: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = provide_liquidity_to_token_native_pool_call(
+            asset_id,
+            native_desired,
+            asset_desired,
+            native_min,
+            asset_min,
+            self.signer.account_id(),
+        );
+        self.submit(call).await
+    }
+
+    // Burns `lp_token_burn` of the signer's LP tokens of the native/asset pool
+    // for at least the given amounts of each side, sent to the signer
+    pub async fn remove_liquidity(
+        &self,
+        asset_id: u32,
+        lp_token_burn: u128,
+        native_min: u128,
+        asset_min: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = remove_liquidity_call(
+            native_location(),
+            local_asset_location(asset_id),
+            lp_token_burn,
+            native_min,
+            asset_min,
+            self.signer.account_id(),
+        );
+        self.submit(call).await
+    }
+
+    // Transfers `amount` native plancks to `dest`, keeping the signer alive,
+    // with the fee paid in `fee_asset` through its pool with the native token
+    // when given. Like the other transfers, it refuses amounts below the
+    // client's minimum, see `with_min_amount`.
+    pub async fn transfer(
+        &self,
+        dest: AccountId32,
+        amount: u128,
+        fee_asset: Option<MultiLocation>,
+    ) -> Result<SubmissionReceipt, AppError> {
+        check_amount(amount, self.min_amount)?;
+        let call = Call::Balances(BalancesCall::transfer_keep_alive {
+            dest: dest.into(),
+            value: amount,
+        });
+        self.submit_paying(call, self.tip, fee_asset).await
+    }
+
+    // Validates what `transfer` would submit, with the same tip, era and fee
+    // asset, instead of submitting it, see `dry_run`
+    pub async fn dry_run_transfer(
+        &self,
+        dest: AccountId32,
+        amount: u128,
+        fee_asset: Option<MultiLocation>,
+    ) -> Result<(), AppError> {
+        check_amount(amount, self.min_amount)?;
+        let call = Call::Balances(BalancesCall::transfer_keep_alive {
+            dest: dest.into(),
+            value: amount,
+        });
+        let builder = params_builder(&self.api(), self.mortality).await?;
+        let tx_config = match fee_asset {
+            Some(asset) => builder.tip_of(self.tip, asset),
+            None => builder.tip(self.tip),
+        }
+        .build();
+        dry_run(&self.api(), &self.signer, &CallPayload(call), tx_config).await
+    }
+
+    // Same as `transfer`, tipping `native_tip` plancks to be prioritized, with
+    // the fee and the tip paid in `asset`. The tip is given in native plancks
+    // as the runtime adds it to the fee before swapping the total for the
+    // asset. The `AssetTxFeePaid` event has to report that tip, and an asset
+    // fee of at least what the tip alone was quoted at beforehand.
+    pub async fn transfer_with_tip(
+        &self,
+        dest: AccountId32,
+        amount: u128,
+        asset: MultiLocation,
+        native_tip: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        check_amount(amount, self.min_amount)?;
+        let tip_in_asset = match native_tip {
+            0 => 0,
+            tip => self.quote_price(asset.clone(), native_location(), tip, QuoteKind::ExactOut, true).await?,
+        };
+
+        let call = Call::Balances(BalancesCall::transfer_keep_alive {
+            dest: dest.into(),
+            value: amount,
+        });
+        let receipt = self.submit_paying(call, native_tip, Some(asset)).await?;
+
+        let paid = receipt
+            .events
+            .find_first::<local::asset_tx_payment::events::AssetTxFeePaid>()?
+            .ok_or_else(|| AppError::UnexpectedOutcome("no `AssetTxFeePaid` event was emitted".into()))?;
+        if paid.tip != native_tip || paid.actual_fee < tip_in_asset {
+            return Err(AppError::UnexpectedOutcome(format!(
+                "the fee paid was {} of the asset with a tip of {}, expected the tip {native_tip}, worth {tip_in_asset} of the asset",
+                paid.actual_fee, paid.tip
+            )));
+        }
+
+        Ok(receipt)
+    }
+
+    // Transfers `amount` of the asset to `dest`, keeping the signer's account
+    // of it alive. Fails before submitting when the signer holds less, and
+    // when the runtime doesn't report the transfer it was asked for.
+    pub async fn transfer_asset(
+        &self,
+        asset_id: u32,
+        dest: MultiAddress<AccountId32, ()>,
+        amount: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        check_amount(amount, self.min_amount)?;
+        let who = self.signer.account_id();
+        let balance = self.asset_balance(asset_id, &who).await?;
+        if amount > balance {
+            return Err(AppError::Invalid(format!(
+                "can't transfer {amount} of the asset {asset_id}, {who} only holds {balance}"
+            )));
+        }
+
+        let call = transfer_asset_call(asset_id, dest, amount);
+        let receipt = self.submit(call).await?;
+
+        let transferred = receipt
+            .events
+            .find_first::<local::assets::events::Transferred>()?
+            .ok_or_else(|| AppError::UnexpectedOutcome("no `Transferred` event was emitted".into()))?;
+        if transferred.asset_id != asset_id || transferred.amount != amount {
+            return Err(AppError::UnexpectedOutcome(format!(
+                "the runtime transferred {} of the asset {} instead",
+                transferred.amount, transferred.asset_id
+            )));
+        }
+
+        Ok(receipt)
+    }
+
+    // Swaps exactly `amount_in` of the first asset of `path` for at least
+    // `amount_out_min` of the last one, sent to the signer
+    pub async fn swap(
+        &self,
+        path: Vec<MultiLocation>,
+        amount_in: u128,
+        amount_out_min: u128,
+    ) -> Result<SubmissionReceipt, AppError> {
+        let call = swap_exact_tokens_for_tokens_call(path, amount_in, amount_out_min, self.signer.account_id(), true);
+        self.submit(call).await
+    }
+
+    // Native fee of submitting `call`, e.g. a batch or a swap about to be
+    // submitted, see `estimate_fee`
+    pub async fn estimate_fee<P: Payload>(&self, call: &P) -> Result<u128, AppError> {
+        self.client.retry(|api| async move { estimate_fee(&api, call).await }).await
+    }
+
+    // Native fee of transferring `amount` plancks to `dest`
+    pub async fn estimate_transfer_fee(&self, dest: MultiAddress<AccountId32, ()>, amount: u128) -> Result<u128, AppError> {
+        self.client.retry(|api| estimate_fees(api, dest.clone(), amount)).await
+    }
+
+    // Fee of transferring `amount` native plancks to `dest`, quoted in
+    // `asset` by its pool with the native token, plus the client's buffer
+    pub async fn estimate_fee_in_asset(
+        &self,
+        dest: MultiAddress<AccountId32, ()>,
+        amount: u128,
+        asset: MultiLocation,
+    ) -> Result<u128, AppError> {
+        let native_fee = self.estimate_transfer_fee(dest, amount).await?;
+        let fee = self
+            .quote_price(native_location(), asset, native_fee, QuoteKind::ExactIn, true)
+            .await?;
+
+        Ok(with_fee_buffer(fee, self.fee_buffer_percent))
+    }
+
+    // Value of `amount` native plancks in the asset, by its pool with the
+    // native token, see `convert_fees`. Unlike `estimate_fee_in_asset` no
+    // buffer is added.
+    pub async fn convert_fees(&self, asset_id: u32, amount: u128) -> Result<u128, AppError> {
+        self.client.retry(|api| convert_fees(api, asset_id, amount)).await
+    }
+
+    // Price of swapping `from` for `to` by the `AssetConversionApi`: what
+    // `amount` of `from` buys of `to` for `ExactIn`, or how much of `from` it
+    // takes to get `amount` of `to` for `ExactOut`
+    pub async fn quote_price(
+        &self,
+        from: MultiLocation,
+        to: MultiLocation,
+        amount: u128,
+        exact: QuoteKind,
+        include_fee: bool,
+    ) -> Result<u128, AppError> {
+        let quote = self
+            .client
+            .retry(|api| {
+                let (from, to) = (from.clone(), to.clone());
+                async move {
+                    let runtime_api = api.runtime_api().at_latest().await?;
+                    let quote = match exact {
+                        QuoteKind::ExactIn => {
+                            let call = local::apis()
+                                .asset_conversion_api()
+                                .quote_price_exact_tokens_for_tokens(from, to, amount, include_fee);
+                            runtime_api.call(call).await?
+                        }
+                        QuoteKind::ExactOut => {
+                            let call = local::apis()
+                                .asset_conversion_api()
+                                .quote_price_tokens_for_exact_tokens(from, to, amount, include_fee);
+                            runtime_api.call(call).await?
+                        }
+                    };
+                    Ok(quote)
+                }
+            })
+            .await?;
+
+        quote.ok_or_else(|| {
+            AppError::QuoteUnavailable(format!("Could not quote {amount} ({exact:?}) from {from:?} to {to:?}"))
+        })
+    }
+
+    // Prints the `AssetConversion` events of the finalized blocks as they're
+    // finalized, see `watch::watch_finalized`. A lost connection is
+    // re-established as `retry` does, up to the client's `max_retries` times
+    // over a subscription, and a subscription the node ends is opened again.
+    pub async fn watch_asset_conversion_events(&self, native_decimals: u8) -> Result<(), AppError> {
+        let last = Cell::new(None);
+        loop {
+            self.client.retry(|api| watch_finalized(api, &last, native_decimals)).await?;
+            warn!("The node ended the subscription to the finalized blocks, subscribing again");
+        }
+    }
+
+    // Checks the connected runtime's pallets match the compiled metadata, see
+    // `check_metadata_compatibility`
+    pub async fn check_metadata_compatibility(&self) -> Result<(), AppError> {
+        check_metadata_compatibility(&self.api()).await
+    }
+
+    // Whether the asset has already been created
+    pub async fn asset_exists(&self, asset_id: u32) -> Result<bool, AppError> {
+        self.client.retry(|api| async move { asset_exists(&api, asset_id).await }).await
+    }
+
+    // Whether there's a pool between the two assets, given in the order of
+    // its key in `AssetConversion::Pools`
+    pub async fn pool_exists(&self, asset1: MultiLocation, asset2: MultiLocation) -> Result<bool, AppError> {
+        self.client.retry(|api| pool_exists(api, asset1.clone(), asset2.clone())).await
+    }
+
+    // Balance of the asset held by `who`, 0 when it holds none
+    pub async fn asset_balance(&self, asset_id: u32, who: &AccountId32) -> Result<u128, AppError> {
+        self.client.retry(|api| async move { asset_balance(&api, asset_id, who).await }).await
+    }
+
+    // Reserves of the pool between the two assets, in the order of the pool's
+    // key in `AssetConversion::Pools` (the native token first for native
+    // pools) whichever order the assets are given in. These are the balances
+    // the pool account holds of each asset, as reported by the runtime.
+    pub async fn pool_reserves(&self, asset1: MultiLocation, asset2: MultiLocation) -> Result<(u128, u128), AppError> {
+        self.client
+            .retry(|api| Self::read_pool_reserves(api, asset1.clone(), asset2.clone()))
+            .await
+    }
+
+    // Follows the finalized blocks until both reserves of the pool between the
+    // two assets are at least `min_reserve`, e.g. for a swap right after the
+    // liquidity was added to see it. A pool that doesn't exist yet counts as
+    // empty.
+    pub async fn wait_for_pool_liquidity(
+        &self,
+        asset1: MultiLocation,
+        asset2: MultiLocation,
+        min_reserve: u128,
+        timeout: Duration,
+    ) -> Result<(), AppError> {
+        let wait = async {
+            let mut blocks = self.api().blocks().subscribe_finalized().await?;
+            loop {
+                match self.pool_reserves(asset1.clone(), asset2.clone()).await {
+                    Ok((reserve1, reserve2)) if reserve1.min(reserve2) >= min_reserve => return Ok(()),
+                    Ok(_) | Err(AppError::QuoteUnavailable(_)) => {}
+                    Err(e) => return Err(e),
+                }
+                match blocks.next().await {
+                    Some(block) => {
+                        block?;
+                    }
+                    None => blocks = self.api().blocks().subscribe_finalized().await?,
+                }
+            }
+        };
+
+        tokio::time::timeout(timeout, wait).await.map_err(|_| {
+            AppError::Timeout(format!(
+                "the pool between {asset1:?} and {asset2:?} has no {min_reserve} of each asset after {timeout:?}"
+            ))
+        })?
+    }
+
+    async fn read_pool_reserves(
+        api: OnlineClient<CustomConfig>,
+        asset1: MultiLocation,
+        asset2: MultiLocation,
+    ) -> Result<(u128, u128), AppError> {
+        let storage = api.storage().at_latest().await?;
+
+        let mut pool = None;
+        for (first, second) in [(&asset1, &asset2), (&asset2, &asset1)] {
+            if pool_info(&storage, first.clone(), second.clone()).await?.is_some() {
+                pool = Some((first.clone(), second.clone()));
+                break;
+            }
+        }
+        let Some((first, second)) = pool else {
+            return Err(AppError::QuoteUnavailable(format!(
+                "There's no pool between {asset1:?} and {asset2:?}"
+            )));
+        };
+
+        let reserves = local::apis().asset_conversion_api().get_reserves(first, second);
+        let reserves = api.runtime_api().at_latest().await?.call(reserves).await?;
+
+        reserves.ok_or_else(|| {
+            AppError::QuoteUnavailable(format!("The pool between {asset1:?} and {asset2:?} has no reserves"))
+        })
+    }
+
+    // Re-reads the signer's nonce from the chain, e.g. after a transaction
+    // was rejected for a stale or future nonce
+    pub async fn reset_nonce(&self) -> Result<u64, AppError> {
+        let account = local::storage().system().account(self.signer.account_id());
+        let nonce = self
+            .client
+            .retry(|api| {
+                let account = account.clone();
+                async move {
+                    let info = api.storage().at_latest().await?.fetch(&account).await?;
+                    Ok(info.map_or(0, |info| info.nonce.into()))
+                }
+            })
+            .await?;
+
+        self.nonce.lock().unwrap().next = Some(nonce);
+        Ok(nonce)
+    }
+
+    async fn next_nonce(&self) -> Result<u64, AppError> {
+        if let Some(nonce) = self.nonce.lock().unwrap().take() {
+            return Ok(nonce);
+        }
+        self.reset_nonce().await?;
+        Ok(self.nonce.lock().unwrap().take().unwrap_or_default())
+    }
+
+    // Signs the call with the next nonce and waits for it to be finalized. A
+    // transaction that doesn't go through may not have used its nonce, so the
+    // nonce is read again from the chain for the next one. When the
+    // connection is lost the client reconnects and submits the same signed
+    // bytes again, unless they already made it into a block, within the
+    // retries of the client's `SubmitPolicy`.
+    async fn submit(&self, call: Call) -> Result<SubmissionReceipt, AppError> {
+        self.submit_paying(call, self.tip, None).await
+    }
+
+    // Same as `submit`, tipping `tip` native plancks instead of the client's
+    // tip, and with the fee and the tip paid in `fee_asset` when given
+    async fn submit_paying(
+        &self,
+        call: Call,
+        tip: u128,
+        fee_asset: Option<MultiLocation>,
+    ) -> Result<SubmissionReceipt, AppError> {
+        self.policy.check()?;
+        let builder = params_builder(&self.api(), self.mortality).await?;
+        let nonce = self.next_nonce().await?;
+        let builder = builder.nonce(nonce);
+        let tx_config = match fee_asset {
+            Some(asset) => builder.tip_of(tip, asset),
+            None => builder.tip(tip),
+        }
+        .build();
+
+        let result = match create_verified(&self.api(), &CallPayload(call), &self.signer, tx_config).await {
+            Ok(signed) => submit_reconnecting(&self.client, &self.policy, signed).await,
+            Err(e) => Err(e),
+        };
+
+        if result.is_err() {
+            self.nonce.lock().unwrap().next = None;
+        }
+        result
+    }
+}
+
+// The call itself when there's only one, or else an atomic batch of them
+fn batch_of(mut calls: Vec<Call>) -> Call {
+    if calls.len() == 1 {
+        calls.remove(0)
+    } else {
+        Call::Utility(local::utility::Call::batch_all { calls })
+    }
+}
@@ -0,0 +1,58 @@
+use subxt::{tx::Signer, utils::AccountId32};
+use tracing::info;
+
+use crate::{
+    client::{AssetHubClient, QuoteKind},
+    error::AppError,
+    local_asset_location, native_location, CustomConfig, MultiLocation,
+};
+
+// The subcommands that are a call of the client, each submitted on its own,
+// untipped
+
+// With `validate_only` the transfer is validated instead of submitted
+pub async fn transfer<S: Signer<CustomConfig>>(
+    client: &AssetHubClient<S>,
+    asset_id: u32,
+    dest: AccountId32,
+    amount: u128,
+    fee_in_asset: bool,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let fee_asset = fee_in_asset.then(|| local_asset_location(asset_id));
+    if validate_only {
+        return client.dry_run_transfer(dest, amount, fee_asset).await;
+    }
+
+    let receipt = client.transfer(dest, amount, fee_asset).await?;
+    info!(amount, fee_in_asset, block = ?receipt.block_hash, extrinsic = ?receipt.extrinsic_hash, "Transfer finalized");
+    Ok(())
+}
+
+// Prints the quote alone on stdout, so it can be piped
+pub async fn quote<S: Signer<CustomConfig>>(
+    client: &AssetHubClient<S>,
+    asset_id: u32,
+    amount: u128,
+    from: Option<MultiLocation>,
+    to: Option<MultiLocation>,
+    exact_out: bool,
+) -> Result<(), AppError> {
+    let from = from.unwrap_or_else(native_location);
+    let to = to.unwrap_or_else(|| local_asset_location(asset_id));
+    let kind = if exact_out { QuoteKind::ExactOut } else { QuoteKind::ExactIn };
+
+    println!("{}", client.quote_price(from, to, amount, kind, true).await?);
+    Ok(())
+}
+
+// Runs until Ctrl-C, or until the client gives up reconnecting
+pub async fn watch<S: Signer<CustomConfig>>(client: &AssetHubClient<S>, native_decimals: u8) -> Result<(), AppError> {
+    tokio::select! {
+        result = client.watch_asset_conversion_events(native_decimals) => result,
+        _ = tokio::signal::ctrl_c() => {
+            info!("Stopped watching");
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use subxt::{utils::AccountId32, OnlineClient};
+use tracing::{info, warn};
+
+use crate::{error::AppError, local, units::format_units, CustomConfig, MultiLocation};
+
+// Amount of native plancks quoted on every endpoint, about the size of a fee
+const QUOTE_AMOUNT: u128 = 1_000_000_000;
+
+// The read-only state of the example as seen by one endpoint
+#[derive(Debug)]
+struct Snapshot {
+    block: u32,
+    quote: Option<u128>,
+    reserves: Option<(u128, u128)>,
+    native_balance: u128,
+    asset_balance: u128,
+    // Of the asset's metadata, which the asset amounts are displayed with
+    asset_decimals: u8,
+}
+
+impl Snapshot {
+    fn rows(&self, native_token: &(u8, String)) -> [(&'static str, String); 5] {
+        let (native_decimals, native_symbol) = native_token;
+        let asset = |amount| format_units(amount, self.asset_decimals);
+        [
+            ("block", self.block.to_string()),
+            ("quote", self.quote.map_or_else(|| "none".to_string(), asset)),
+            (
+                "reserves",
+                self.reserves.map_or_else(
+                    || "none".to_string(),
+                    |(native, reserve)| format!("{} {native_symbol} / {}", format_units(native, *native_decimals), asset(reserve)),
+                ),
+            ),
+            ("native balance", format!("{} {native_symbol}", format_units(self.native_balance, *native_decimals))),
+            ("asset balance", asset(self.asset_balance)),
+        ]
+    }
+}
+
+async fn snapshot(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    native: &MultiLocation,
+    asset: &MultiLocation,
+    account: &AccountId32,
+) -> Result<Snapshot, AppError> {
+    // Everything is read at the same block so the values are consistent
+    let block = api.blocks().at_latest().await?;
+    let storage = api.storage().at(block.reference());
+    let runtime_api = api.runtime_api().at(block.reference());
+
+    let quote = runtime_api
+        .call(local::apis().asset_conversion_api().quote_price_exact_tokens_for_tokens(
+            native.clone(),
+            asset.clone(),
+            QUOTE_AMOUNT,
+            true,
+        ))
+        .await?;
+
+    let reserves = runtime_api
+        .call(local::apis().asset_conversion_api().get_reserves(native.clone(), asset.clone()))
+        .await?;
+
+    let native_balance = storage
+        .fetch(&local::storage().system().account(account.clone()))
+        .await?
+        .map(|info| info.data.free)
+        .unwrap_or(0);
+
+    let asset_balance = storage
+        .fetch(&local::storage().assets().account(asset_id, account.clone()))
+        .await?
+        .map(|account| account.balance)
+        .unwrap_or(0);
+
+    let asset_decimals = storage
+        .fetch(&local::storage().assets().metadata(asset_id))
+        .await?
+        .map(|metadata| metadata.decimals)
+        .unwrap_or(0);
+
+    Ok(Snapshot {
+        block: block.number(),
+        quote,
+        reserves,
+        native_balance,
+        asset_balance,
+        asset_decimals,
+    })
+}
+
+// Runs the read-only queries of the example (quote, pool reserves and the
+// account's balances) against every endpoint and prints them side by side,
+// flagging any value that differs from the first (designated) endpoint.
+// Endpoints can legitimately disagree while they're at different blocks, so
+// the block each one answered at is part of the comparison.
+pub async fn compare_endpoints(
+    urls: &[String],
+    asset_id: u32,
+    native: MultiLocation,
+    asset: MultiLocation,
+    account: AccountId32,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    let mut snapshots = Vec::new();
+    for url in urls {
+        let api = OnlineClient::<CustomConfig>::from_url(url)
+            .await
+            .map_err(|source| AppError::Connection { url: url.clone(), source })?;
+        snapshots.push(snapshot(&api, asset_id, &native, &asset, &account).await?);
+    }
+
+    let quote = format!("{} {}", format_units(QUOTE_AMOUNT, native_token.0), native_token.1);
+    let reference = snapshots[0].rows(native_token);
+    let mut mismatches = 0;
+    for (url, snapshot) in urls.iter().zip(&snapshots) {
+        for ((label, value), (_, expected)) in snapshot.rows(native_token).iter().zip(&reference) {
+            if value != expected {
+                mismatches += 1;
+                warn!(%url, label, %value, %expected, "Endpoint mismatch");
+            } else {
+                info!(%url, label, %value, %quote, "Endpoint value");
+            }
+        }
+    }
+
+    if mismatches > 0 {
+        warn!(mismatches, "Values differ from {}", urls[0]);
+    } else {
+        info!("All endpoints agree");
+    }
+
+    Ok(())
+}
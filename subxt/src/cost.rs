@@ -0,0 +1,118 @@
+use subxt::OnlineClient;
+
+use crate::{
+    asset_decimals, asset_exists, cli::AssetConfig, convert_fees, error::AppError, estimate_fee, local, local_asset_location, native_location,
+    pool_exists, units::format_units, Call, CustomConfig,
+};
+
+// Everything the setup takes from the signer's native balance. What's only
+// paid when creating the asset or the pool is 0 when it already exists.
+pub struct SetupCost {
+    pub asset_deposit: u128,
+    pub metadata_deposit: u128,
+    pub pool_setup_fee: u128,
+    // Deposits for the pool account's accounts in the asset and in its LP
+    // token, plus the existential deposit it's funded with
+    pub pool_account_deposits: u128,
+    pub liquidity: u128,
+    pub tx_fee: u128,
+    // Kept free so the signer's account stays alive
+    pub existential_deposit: u128,
+    // The total in the custom asset, when there's already a pool to quote it
+    pub total_in_asset: Option<u128>,
+    // How amounts of the custom asset are displayed
+    pub asset_decimals: u8,
+    pub asset_symbol: String,
+}
+
+impl SetupCost {
+    pub fn total(&self) -> u128 {
+        [
+            self.metadata_deposit,
+            self.pool_setup_fee,
+            self.pool_account_deposits,
+            self.liquidity,
+            self.tx_fee,
+            self.existential_deposit,
+        ]
+        .into_iter()
+        .fold(self.asset_deposit, u128::saturating_add)
+    }
+
+    // Renders the cost line by line, in the native token
+    pub fn summary(&self, native_decimals: u8, native_symbol: &str) -> String {
+        let mut summary = String::from("Setup budget:\n");
+        for (label, amount) in [
+            ("asset deposit", self.asset_deposit),
+            ("metadata deposit", self.metadata_deposit),
+            ("pool setup fee", self.pool_setup_fee),
+            ("pool deposits", self.pool_account_deposits),
+            ("liquidity", self.liquidity),
+            ("transaction fee", self.tx_fee),
+            ("existential dep.", self.existential_deposit),
+            ("total", self.total()),
+        ] {
+            summary.push_str(&format!("  {label:<17} {} {native_symbol}\n", format_units(amount, native_decimals)));
+        }
+        if let Some(total_in_asset) = self.total_in_asset {
+            summary.push_str(&format!(
+                "  {:<17} {} {}\n",
+                "total",
+                format_units(total_in_asset, self.asset_decimals),
+                self.asset_symbol
+            ));
+        }
+
+        summary
+    }
+}
+
+// Adds up the deposits the setup reserves (read from the runtime constants),
+// the pool setup fee, the native liquidity and the fee of the setup's `calls`,
+// estimated as one batch. The deposits of what already exists are left out,
+// as the setup skips creating it. When the pool already exists the total is also
+// quoted in the asset, with the decimals of its metadata on chain.
+pub async fn total_setup_cost(
+    api: &OnlineClient<CustomConfig>,
+    asset: &AssetConfig,
+    calls: &[Call],
+    liquidity_native: u128,
+) -> Result<SetupCost, AppError> {
+    let constants = api.constants();
+    let assets = local::constants().assets();
+    let asset_existed = asset_exists(api, asset.id).await?;
+    let pool_existed = pool_exists(api.clone(), native_location(), local_asset_location(asset.id)).await?;
+    let unless = |existed: bool, amount: u128| if existed { 0 } else { amount };
+
+    let metadata_bytes = (asset.name.len() + asset.symbol.len()) as u128;
+    let metadata_deposit = constants
+        .at(&assets.metadata_deposit_base())?
+        .saturating_add(constants.at(&assets.metadata_deposit_per_byte())?.saturating_mul(metadata_bytes));
+
+    let existential_deposit = constants.at(&local::constants().balances().existential_deposit())?;
+    let pool_account_deposits = constants
+        .at(&assets.asset_account_deposit())?
+        .saturating_add(constants.at(&local::constants().pool_assets().asset_account_deposit())?)
+        .saturating_add(existential_deposit);
+
+    let tx_fee = estimate_fee(api, &local::tx().utility().batch_all(calls.to_vec())).await?;
+
+    let mut cost = SetupCost {
+        asset_deposit: unless(asset_existed, constants.at(&assets.asset_deposit())?),
+        metadata_deposit: unless(asset_existed, metadata_deposit),
+        pool_setup_fee: unless(pool_existed, constants.at(&local::constants().asset_conversion().pool_setup_fee())?),
+        pool_account_deposits: unless(pool_existed, pool_account_deposits),
+        liquidity: liquidity_native,
+        tx_fee,
+        existential_deposit,
+        total_in_asset: None,
+        asset_decimals: asset.decimals,
+        asset_symbol: asset.symbol.clone(),
+    };
+    if asset_existed {
+        cost.asset_decimals = asset_decimals(api, asset.id).await?;
+    }
+    cost.total_in_asset = convert_fees(api.clone(), asset.id, cost.total()).await.ok();
+
+    Ok(cost)
+}
@@ -0,0 +1,128 @@
+use subxt::utils::{AccountId32, MultiAddress};
+
+use crate::{cli::BatchMode, AssetConversionCall, AssetsCall, Call};
+
+// Renders the calls of a batch as an indented tree, one node per call
+// with its pallet, method and the arguments worth double checking (ids,
+// amounts, addresses), so the setup can be reviewed before it's signed:
+//
+//   utility.batch_all (2 calls)
+//     [0] Assets.create
+//         id: 1
+//         admin: 5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY
+//         min_balance: 1
+//     [1] ...
+pub fn describe_batch(calls: &[Call], mode: BatchMode) -> String {
+    let batch = match mode {
+        BatchMode::All => "batch_all",
+        BatchMode::BestEffort => "force_batch",
+    };
+    let mut tree = format!("utility.{batch} ({} calls)\n", calls.len());
+
+    for (index, call) in calls.iter().enumerate() {
+        let (pallet, method, args) = describe_call(call);
+        tree.push_str(&format!("  [{index}] {pallet}.{method}\n"));
+        for (name, value) in args {
+            tree.push_str(&format!("      {name}: {value}\n"));
+        }
+    }
+
+    tree
+}
+
+// Splits a call into its pallet, method and key arguments. The calls used by
+// the setup get a readable rendering, anything else falls back to the debug
+// representation derived for the generated types.
+fn describe_call(call: &Call) -> (String, String, Vec<(&'static str, String)>) {
+    match call {
+        Call::Assets(AssetsCall::create { id, admin, min_balance }) => (
+            "Assets".into(),
+            "create".into(),
+            vec![
+                ("id", id.to_string()),
+                ("admin", address(admin)),
+                ("min_balance", min_balance.to_string()),
+            ],
+        ),
+        Call::Assets(AssetsCall::set_metadata { id, name, symbol, decimals }) => (
+            "Assets".into(),
+            "set_metadata".into(),
+            vec![
+                ("id", id.to_string()),
+                ("name", String::from_utf8_lossy(name).into_owned()),
+                ("symbol", String::from_utf8_lossy(symbol).into_owned()),
+                ("decimals", decimals.to_string()),
+            ],
+        ),
+        Call::Assets(AssetsCall::mint { id, beneficiary, amount }) => (
+            "Assets".into(),
+            "mint".into(),
+            vec![
+                ("id", id.to_string()),
+                ("beneficiary", address(beneficiary)),
+                ("amount", amount.to_string()),
+            ],
+        ),
+        Call::Assets(AssetsCall::touch_other { id, who }) => (
+            "Assets".into(),
+            "touch_other".into(),
+            vec![("id", id.to_string()), ("who", address(who))],
+        ),
+        Call::Assets(AssetsCall::set_team { id, issuer, admin, freezer }) => (
+            "Assets".into(),
+            "set_team".into(),
+            vec![
+                ("id", id.to_string()),
+                ("issuer", address(issuer)),
+                ("admin", address(admin)),
+                ("freezer", address(freezer)),
+            ],
+        ),
+        Call::AssetConversion(AssetConversionCall::create_pool { asset1, asset2 }) => (
+            "AssetConversion".into(),
+            "create_pool".into(),
+            vec![
+                ("asset1", format!("{asset1:?}")),
+                ("asset2", format!("{asset2:?}")),
+            ],
+        ),
+        Call::AssetConversion(AssetConversionCall::add_liquidity {
+            asset1,
+            asset2,
+            amount1_desired,
+            amount2_desired,
+            amount1_min,
+            amount2_min,
+            mint_to,
+        }) => (
+            "AssetConversion".into(),
+            "add_liquidity".into(),
+            vec![
+                ("asset1", format!("{asset1:?}")),
+                ("asset2", format!("{asset2:?}")),
+                ("amount1_desired", amount1_desired.to_string()),
+                ("amount2_desired", amount2_desired.to_string()),
+                ("amount1_min", amount1_min.to_string()),
+                ("amount2_min", amount2_min.to_string()),
+                ("mint_to", mint_to.to_string()),
+            ],
+        ),
+        other => {
+            // The debug output looks like `Pallet(method { .. })`
+            let debug = format!("{other:?}");
+            let (pallet, rest) = debug.split_once('(').unwrap_or((&debug, ""));
+            let method = rest
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or_default();
+            (pallet.to_string(), method.to_string(), vec![("call", debug.clone())])
+        }
+    }
+}
+
+fn address(address: &MultiAddress<AccountId32, ()>) -> String {
+    match address {
+        MultiAddress::Id(account) => account.to_string(),
+        other => format!("{other:?}"),
+    }
+}
@@ -0,0 +1,353 @@
+use codec::Encode;
+use subxt::{
+    dynamic::{At, Value},
+    config::ExtrinsicParams,
+    ext::scale_value,
+    metadata::types::StorageEntryType,
+    tx::{DynamicPayload, Signer},
+    utils::AccountId32,
+    Metadata, OnlineClient,
+};
+use tracing::info;
+
+use crate::{
+    apply_slippage, check_amount,
+    cli::{AssetConfig, Cli, Command, SetupParams},
+    client::QuoteKind,
+    error::AppError,
+    dry_run, local_asset_location, native_location, params_builder, signer::create_verified, submit_idempotent, CustomConfig,
+    MortalityConfig, MultiLocation, SubmissionReceipt, SubmitPolicy, WestmintExtrinsicParams,
+};
+
+// The `--dynamic` path: the calls and the runtime API are built from the
+// metadata the node serves instead of the one compiled into the example, so
+// it keeps working across runtime upgrades as long as the pallets still have
+// the calls, by name, with compatible fields. Each call is looked up before
+// it's built, and a missing one fails with `AppError::MetadataMismatch`.
+
+// Checks `pallet` has the call `call` in the node's metadata
+fn check_call(metadata: &Metadata, pallet: &str, call: &str) -> Result<(), AppError> {
+    metadata
+        .pallet_by_name(pallet)
+        .ok_or_else(|| AppError::MetadataMismatch(format!("the runtime has no {pallet} pallet")))?
+        .call_variant_by_name(call)
+        .map(|_| ())
+        .ok_or_else(|| AppError::MetadataMismatch(format!("{pallet} has no {call} call")))
+}
+
+// Type id of the field `field` of `pallet`'s call `call`
+fn call_field_type(metadata: &Metadata, pallet: &str, call: &str, field: &str) -> Result<u32, AppError> {
+    check_call(metadata, pallet, call)?;
+    metadata
+        .pallet_by_name(pallet)
+        .and_then(|pallet| pallet.call_variant_by_name(call))
+        .and_then(|variant| variant.fields.iter().find(|f| f.name.as_deref() == Some(field)))
+        .map(|f| f.ty.id)
+        .ok_or_else(|| AppError::MetadataMismatch(format!("{pallet}::{call} has no {field} field")))
+}
+
+// Re-reads `value`, SCALE encoded as the compiled type, as the node's type
+// `type_id`. That's how the locations are handed over, whichever XCM version
+// the runtime names them with, as long as their encoding didn't change.
+fn as_runtime_type(metadata: &Metadata, type_id: u32, value: &impl Encode) -> Result<Value, AppError> {
+    let encoded = value.encode();
+    let cursor = &mut &encoded[..];
+    let decoded = scale_value::scale::decode_as_type(cursor, type_id, metadata.types())
+        .map_err(|e| AppError::MetadataMismatch(format!("the runtime's type {type_id} doesn't decode it ({e})")))?;
+    if !cursor.is_empty() {
+        return Err(AppError::MetadataMismatch(format!("the runtime's type {type_id} is shorter than it")));
+    }
+    Ok(decoded.remove_context())
+}
+
+fn account(who: &AccountId32) -> Value {
+    Value::from_bytes(who.0)
+}
+
+// `MultiAddress::Id`, how the calls below take their accounts
+fn address(who: &AccountId32) -> Value {
+    Value::unnamed_variant("Id", [account(who)])
+}
+
+// The signed extensions of the dynamic transactions: untipped like the other
+// single operations, paying the fee in `fee_asset` when given
+async fn tx_params(
+    api: &OnlineClient<CustomConfig>,
+    fee_asset: Option<MultiLocation>,
+    mortality: Option<MortalityConfig>,
+) -> Result<<WestmintExtrinsicParams as ExtrinsicParams<CustomConfig>>::Params, AppError> {
+    let builder = params_builder(api, mortality).await?;
+    Ok(match fee_asset {
+        Some(asset) => builder.tip_of(0, asset),
+        None => builder,
+    }
+    .build())
+}
+
+// Signs `payload` for the chain's current signed extensions, see `tx_params`,
+// and waits for it to be finalized
+async fn submit<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    payload: DynamicPayload,
+    fee_asset: Option<MultiLocation>,
+    mortality: Option<MortalityConfig>,
+) -> Result<SubmissionReceipt, AppError> {
+    let params = tx_params(api, fee_asset, mortality).await?;
+    let tx = create_verified(api, &payload, signer, params).await?;
+    submit_idempotent(api, policy, tx).await
+}
+
+// `Assets::create`, with the signer's `admin` as the asset's admin
+pub fn create_asset(metadata: &Metadata, asset_id: u32, admin: &AccountId32, min_balance: u128) -> Result<Value, AppError> {
+    check_call(metadata, "Assets", "create")?;
+    let fields = vec![("id", Value::u128(asset_id.into())), ("admin", address(admin)), ("min_balance", Value::u128(min_balance))];
+    Ok(subxt::dynamic::tx("Assets", "create", fields).into_value())
+}
+
+pub fn set_metadata(metadata: &Metadata, asset_config: &AssetConfig) -> Result<Value, AppError> {
+    check_call(metadata, "Assets", "set_metadata")?;
+    let fields = vec![
+        ("id", Value::u128(asset_config.id.into())),
+        ("name", Value::from_bytes(asset_config.name.as_bytes())),
+        ("symbol", Value::from_bytes(asset_config.symbol.as_bytes())),
+        ("decimals", Value::u128(asset_config.decimals.into())),
+    ];
+    Ok(subxt::dynamic::tx("Assets", "set_metadata", fields).into_value())
+}
+
+// `Assets::mint` of `amount` to `beneficiary`
+pub fn mint(metadata: &Metadata, asset_id: u32, beneficiary: &AccountId32, amount: u128) -> Result<Value, AppError> {
+    check_call(metadata, "Assets", "mint")?;
+    let fields = vec![("id", Value::u128(asset_id.into())), ("beneficiary", address(beneficiary)), ("amount", Value::u128(amount))];
+    Ok(subxt::dynamic::tx("Assets", "mint", fields).into_value())
+}
+
+// `AssetConversion::create_pool` pairing the asset with the native token
+pub fn create_pool(metadata: &Metadata, asset_id: u32) -> Result<Value, AppError> {
+    let location_ty = call_field_type(metadata, "AssetConversion", "create_pool", "asset1")?;
+    let fields = vec![
+        ("asset1", as_runtime_type(metadata, location_ty, &native_location::<MultiLocation>())?),
+        ("asset2", as_runtime_type(metadata, location_ty, &local_asset_location::<MultiLocation>(asset_id))?),
+    ];
+    Ok(subxt::dynamic::tx("AssetConversion", "create_pool", fields).into_value())
+}
+
+// `AssetConversion::add_liquidity` to the native/asset pool, the LP tokens
+// going to `mint_to`
+pub fn add_liquidity(
+    metadata: &Metadata,
+    asset_id: u32,
+    (native_desired, asset_desired): (u128, u128),
+    (native_min, asset_min): (u128, u128),
+    mint_to: &AccountId32,
+) -> Result<Value, AppError> {
+    let location_ty = call_field_type(metadata, "AssetConversion", "add_liquidity", "asset1")?;
+    let fields = vec![
+        ("asset1", as_runtime_type(metadata, location_ty, &native_location::<MultiLocation>())?),
+        ("asset2", as_runtime_type(metadata, location_ty, &local_asset_location::<MultiLocation>(asset_id))?),
+        ("amount1_desired", Value::u128(native_desired)),
+        ("amount2_desired", Value::u128(asset_desired)),
+        ("amount1_min", Value::u128(native_min)),
+        ("amount2_min", Value::u128(asset_min)),
+        ("mint_to", account(mint_to)),
+    ];
+    Ok(subxt::dynamic::tx("AssetConversion", "add_liquidity", fields).into_value())
+}
+
+// Whether `Assets::Asset` has an entry for the asset
+async fn asset_exists(api: &OnlineClient<CustomConfig>, asset_id: u32) -> Result<bool, AppError> {
+    let query = subxt::dynamic::storage("Assets", "Asset", vec![Value::u128(asset_id.into())]);
+    Ok(api.storage().at_latest().await?.fetch(&query).await?.is_some())
+}
+
+// Whether `AssetConversion::Pools` has the native/asset pool, its key being
+// the pair of locations as the runtime types it
+async fn pool_exists(api: &OnlineClient<CustomConfig>, asset_id: u32) -> Result<bool, AppError> {
+    let metadata = api.metadata();
+    let key_ty = metadata
+        .pallet_by_name("AssetConversion")
+        .and_then(|pallet| pallet.storage())
+        .and_then(|storage| storage.entry_by_name("Pools"))
+        .and_then(|entry| match entry.entry_type() {
+            StorageEntryType::Map { key_ty, .. } => Some(*key_ty),
+            StorageEntryType::Plain(_) => None,
+        })
+        .ok_or_else(|| AppError::MetadataMismatch("AssetConversion has no Pools map".into()))?;
+    let pair: (MultiLocation, MultiLocation) = (native_location(), local_asset_location(asset_id));
+    let key = as_runtime_type(&metadata, key_ty, &pair)?;
+
+    let query = subxt::dynamic::storage("AssetConversion", "Pools", vec![key]);
+    Ok(api.storage().at_latest().await?.fetch(&query).await?.is_some())
+}
+
+// The setup of the static path, create, set_metadata, mint, create_pool and
+// add_liquidity, as a `Utility::batch_all` for `who`. What already exists is
+// skipped the same way.
+pub async fn setup_batch(
+    api: &OnlineClient<CustomConfig>,
+    who: &AccountId32,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    slippage_bps: u16,
+) -> Result<DynamicPayload, AppError> {
+    let metadata = api.metadata();
+    let asset_id = asset_config.id;
+    check_call(&metadata, "Utility", "batch_all")?;
+
+    let mut calls = Vec::new();
+    if asset_exists(api, asset_id).await? {
+        info!(asset_id, "The asset already exists, skipping create, set_metadata and mint");
+    } else {
+        calls.push(create_asset(&metadata, asset_id, who, params.min_balance)?);
+        calls.push(set_metadata(&metadata, asset_config)?);
+        calls.push(mint(&metadata, asset_id, who, params.mint_amount)?);
+    }
+    if pool_exists(api, asset_id).await? {
+        info!(asset_id, "The pool already exists, skipping create_pool and going to add_liquidity");
+    } else {
+        calls.push(create_pool(&metadata, asset_id)?);
+    }
+    let desired = (params.liquidity_native, params.liquidity_asset);
+    let min = (apply_slippage(desired.0, slippage_bps)?, apply_slippage(desired.1, slippage_bps)?);
+    calls.push(add_liquidity(&metadata, asset_id, desired, min, who)?);
+
+    Ok(subxt::dynamic::tx("Utility", "batch_all", vec![("calls", Value::unnamed_composite(calls))]))
+}
+
+// `Balances::transfer_keep_alive` of `amount` native plancks to `dest`
+pub fn transfer_call(metadata: &Metadata, dest: &AccountId32, amount: u128) -> Result<DynamicPayload, AppError> {
+    check_call(metadata, "Balances", "transfer_keep_alive")?;
+    Ok(subxt::dynamic::tx("Balances", "transfer_keep_alive", vec![("dest", address(dest)), ("value", Value::u128(amount))]))
+}
+
+// `AssetConversionApi` quote, see `AssetHubClient::quote_price`
+pub async fn quote_price(
+    api: &OnlineClient<CustomConfig>,
+    from: &MultiLocation,
+    to: &MultiLocation,
+    amount: u128,
+    exact: QuoteKind,
+    include_fee: bool,
+) -> Result<u128, AppError> {
+    let method = match exact {
+        QuoteKind::ExactIn => "quote_price_exact_tokens_for_tokens",
+        QuoteKind::ExactOut => "quote_price_tokens_for_exact_tokens",
+    };
+    let metadata = api.metadata();
+    let location_ty = metadata
+        .runtime_api_trait_by_name("AssetConversionApi")
+        .ok_or_else(|| AppError::MetadataMismatch("the runtime has no AssetConversionApi".into()))?
+        .method_by_name(method)
+        .and_then(|method| method.inputs().next())
+        .map(|input| input.ty)
+        .ok_or_else(|| AppError::MetadataMismatch(format!("AssetConversionApi has no {method}")))?;
+
+    let args = vec![
+        as_runtime_type(&metadata, location_ty, from)?,
+        as_runtime_type(&metadata, location_ty, to)?,
+        Value::u128(amount),
+        Value::bool(include_fee),
+    ];
+    let call = subxt::dynamic::runtime_api_call("AssetConversionApi", method, args);
+    let quote = api.runtime_api().at_latest().await?.call(call).await?.to_value().map_err(subxt::Error::from)?;
+
+    // An `Option<Balance>`, `None` when there's no pool or not enough liquidity
+    quote
+        .at(0)
+        .and_then(|balance| balance.as_u128())
+        .ok_or_else(|| AppError::QuoteUnavailable(format!("Could not quote {amount} ({exact:?}) from {from:?} to {to:?}")))
+}
+
+// The subcommands `--dynamic` supports, the rest need the compiled metadata.
+// With `--dry-run` the transaction is only validated, see `dry_run`.
+pub async fn run<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    cli: &Cli,
+    asset_id: u32,
+) -> Result<(), AppError> {
+    let (payload, fee_asset) = match &cli.command {
+        Some(Command::Setup) => {
+            let asset_config = AssetConfig {
+                id: asset_id,
+                name: cli.name.clone(),
+                symbol: cli.symbol.clone(),
+                decimals: cli.decimals,
+            };
+            let params = SetupParams::from_cli(cli);
+            (setup_batch(api, &signer.account_id(), &asset_config, &params, cli.slippage_bps).await?, None)
+        }
+        Some(Command::Transfer { dest, amount, fee_in_asset }) => {
+            check_amount(*amount, cli.min_amount)?;
+            let fee_asset = fee_in_asset.then(|| local_asset_location(asset_id));
+            (transfer_call(&api.metadata(), dest, *amount)?, fee_asset)
+        }
+        Some(Command::Quote { amount, from, to, exact_out }) => {
+            let from = from.clone().unwrap_or_else(native_location);
+            let to = to.clone().unwrap_or_else(|| local_asset_location(asset_id));
+            let kind = if *exact_out { QuoteKind::ExactOut } else { QuoteKind::ExactIn };
+            println!("{}", quote_price(api, &from, &to, *amount, kind, true).await?);
+            return Ok(());
+        }
+        _ => {
+            return Err(AppError::Unsupported(
+                "--dynamic only supports the setup, transfer and quote subcommands".into(),
+            ))
+        }
+    };
+
+    if cli.dry_run {
+        let params = tx_params(api, fee_asset, cli.mortal_period).await?;
+        return dry_run(api, signer, &payload, params).await;
+    }
+    let receipt = submit(api, signer, policy, payload, fee_asset, cli.mortal_period).await?;
+    info!(block = ?receipt.block_hash, extrinsic = ?receipt.extrinsic_hash, "Finalized");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        create_asset_call, create_pool_with_native_call, mint_token_call, provide_liquidity_to_token_native_pool_call,
+        tests::metadata, ASSET_ID,
+    };
+
+    // The `--dynamic` calls, encoded as the runtime's `RuntimeCall`, are the
+    // same bytes as the compiled ones
+    #[test]
+    fn dynamic_calls_match_static_ones() {
+        use subxt::ext::scale_encode::EncodeAsType;
+
+        let metadata = metadata();
+        let call_ty = metadata.outer_enums().call_enum_ty();
+        let who = AccountId32([1; 32]);
+
+        let calls = [
+            (
+                create_asset(&metadata, ASSET_ID, &who, 1).unwrap(),
+                create_asset_call(ASSET_ID, who.clone().into(), 1),
+            ),
+            (
+                mint(&metadata, ASSET_ID, &who, 1000).unwrap(),
+                mint_token_call(ASSET_ID, who.clone().into(), 1000),
+            ),
+            (
+                create_pool(&metadata, ASSET_ID).unwrap(),
+                create_pool_with_native_call(ASSET_ID),
+            ),
+            (
+                add_liquidity(&metadata, ASSET_ID, (100, 10), (99, 9), &who).unwrap(),
+                provide_liquidity_to_token_native_pool_call(ASSET_ID, 100, 10, 99, 9, who.clone()),
+            ),
+        ];
+
+        for (dynamic, expected) in calls {
+            let encoded = dynamic.encode_as_type(call_ty, metadata.types()).unwrap();
+            assert_eq!(encoded, expected.encode(), "{expected:?}");
+        }
+    }
+}
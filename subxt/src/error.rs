@@ -0,0 +1,187 @@
+use subxt::{
+    error::{DispatchError, ExtrinsicParamsError, TokenError},
+    Metadata,
+};
+
+// `create_signed` couldn't build the signed extensions `CustomConfig` sets up
+// for the connected runtime, which happens when the runtime's extensions
+// aren't the ones the example was written against
+#[derive(Debug, thiserror::Error)]
+#[error(
+    "The signed extensions of `CustomConfig` don't match the connected runtime ({source}). \
+     Check that ./metadata/asset_hub_metadata.scale was fetched from this runtime, \
+     regenerate it with `subxt metadata` otherwise, and adjust `CustomConfig` to the extensions it lists"
+)]
+pub struct SignedExtensionMismatch {
+    #[source]
+    source: ExtrinsicParamsError,
+}
+
+// Turns a `create_signed` failure caused by the signed extensions into a
+// `SignedExtensionMismatch`, leaving any other error as it is
+pub fn check_signed_extensions(error: subxt::Error) -> AppError {
+    match error {
+        subxt::Error::ExtrinsicParams(source) => SignedExtensionMismatch { source }.into(),
+        error => error.into(),
+    }
+}
+
+// Errors of the example's main flow: connecting, estimating and converting
+// the fee, setting up the asset and pool and submitting the transfer
+#[derive(thiserror::Error)]
+pub enum AppError {
+    // The node at `url` couldn't be reached
+    #[error("Could not connect to {url}: {source}")]
+    Connection {
+        url: String,
+        #[source]
+        source: subxt::Error,
+    },
+    #[error(transparent)]
+    Subxt(#[from] subxt::Error),
+    #[error(transparent)]
+    SignedExtensions(#[from] SignedExtensionMismatch),
+    // `TransactionPaymentApi_query_info` couldn't estimate the fee
+    #[error("Could not estimate the fee: {0}")]
+    FeeEstimation(#[source] subxt::Error),
+    // There's no pool between the assets, or not enough liquidity to quote
+    #[error("{0}")]
+    QuoteUnavailable(String),
+    // The fee can't be paid with the asset, as no native pool pairs with it
+    #[error("{0}")]
+    FeeAsset(String),
+    // The signer couldn't be set up, e.g. the Ledger isn't reachable
+    #[error("Could not set up the signer: {0}")]
+    Signer(String),
+    // A swap couldn't be quoted or submitted
+    #[error("The swap failed: {0}")]
+    Swap(String),
+    // The swap took more than the limit it was submitted with
+    #[error("The swap consumed {consumed}, above the maximum of {max}")]
+    SwapInputExceeded { consumed: u128, max: u128 },
+    // Validating the transaction, without submitting it, rejected it
+    #[error("The transaction would be rejected: {0}")]
+    Invalid(String),
+    // The node's runtime changed pallets the example was compiled against
+    #[error(
+        "The node's runtime differs from ./metadata/asset_hub_metadata.scale in: {0}. \
+         Regenerate it from this node with `subxt metadata` and rebuild"
+    )]
+    MetadataMismatch(String),
+    // The setup couldn't be sized, funded or submitted
+    #[error("The setup failed: {0}")]
+    Setup(String),
+    // A file given on the command line, a config file or a run log, can't be
+    // read or parsed
+    #[error("Could not read {path}: {reason}")]
+    File { path: String, reason: String },
+    // The command line can't be parsed, or asks for `--help` or `--version`
+    #[error("{0}")]
+    Usage(#[from] clap::Error),
+    // The options given can't be combined
+    #[error("{0}")]
+    Unsupported(String),
+    // Submitting to a chain that isn't a local one wasn't acknowledged
+    #[error("Refused to submit: {0}")]
+    Refused(String),
+    // An amount computation doesn't fit a `u128`
+    #[error("{0} overflows")]
+    ArithmeticOverflow(String),
+    // A transaction was included but didn't do what it was submitted for,
+    // e.g. it emitted no event reporting it
+    #[error("The transaction was included but {0}")]
+    UnexpectedOutcome(String),
+    // The runtime was upgraded during a run pinned to its spec version
+    #[error("The runtime was upgraded from spec version {from} to {to}, aborting")]
+    RuntimeUpgraded { from: u32, to: u32 },
+    // What was being waited for didn't happen in time
+    #[error("Timed out: {0}")]
+    Timeout(String),
+}
+
+// `main` reports its error with `Debug`, which should read like the message
+impl std::fmt::Debug for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+// Names a dispatch error the way the runtime does, `Pallet::Error` along with
+// its docs for the pallet errors, which are otherwise only a pallet and error
+// index, and adds what to do about the ones the example runs into the most
+pub fn classify_dispatch_error(err: &DispatchError, metadata: &Metadata) -> String {
+    let (name, docs) = match err {
+        DispatchError::Module(module_error) => {
+            let (pallet_index, error_index) = (module_error.pallet_index(), module_error.error_index());
+            let pallet = metadata.pallet_by_index(pallet_index);
+            match pallet.and_then(|pallet| Some((pallet.name(), pallet.error_variant_by_index(error_index)?))) {
+                Some((pallet, variant)) => (format!("{pallet}::{}", variant.name), variant.docs.join(" ")),
+                None => (format!("error {error_index} of the pallet {pallet_index}"), String::new()),
+            }
+        }
+        DispatchError::Token(token_error) => (format!("Token::{token_error:?}"), token_error.to_string()),
+        err => (err.to_string(), String::new()),
+    };
+
+    let mut message = name.clone();
+    if !docs.trim().is_empty() {
+        message.push_str(&format!(" ({})", docs.trim()));
+    }
+    if let Some(remediation) = remediation(err, &name) {
+        message.push_str(&format!(". {remediation}"));
+    }
+    message
+}
+
+fn remediation(err: &DispatchError, name: &str) -> Option<&'static str> {
+    if let DispatchError::Token(TokenError::FundsUnavailable | TokenError::BelowMinimum) = err {
+        return Some("The signer can't afford it, fund it or pass --funder");
+    }
+
+    Some(match name {
+        "Assets::InUse" => "The asset id is taken, pick another one with --asset-id",
+        "Assets::BalanceLow" => "The account holds too little of the asset, mint more or lower the amounts",
+        "Assets::NoAccount" => "The account holds none of the asset, it has to receive some or be touched first",
+        "Assets::NoPermission" => "The signer doesn't have the asset role the call needs, see `set_team`",
+        "Assets::MinBalanceZero" => "Pass a --min-balance above 0",
+        "Assets::BadMetadata" => "The name or symbol is longer than the chain allows, shorten them",
+        "Assets::Frozen" | "Assets::AssetNotLive" => "The asset is frozen or being destroyed, thaw it with `admin thaw`",
+        "AssetConversion::PoolExists" => "The pool is already there, running the setup again skips create_pool",
+        "AssetConversion::PoolNotFound" => "There's no pool for the pair yet, run the setup first",
+        "AssetConversion::AssetOneDepositDidNotMeetMinimum" | "AssetConversion::AssetTwoDepositDidNotMeetMinimum" => {
+            "The pool's price moved, raise --slippage-bps or use --staged-setup with --liquidity-retries"
+        }
+        "AssetConversion::AmountOneLessThanMinimal" | "AssetConversion::AmountTwoLessThanMinimal" => {
+            "The liquidity is too small for the pool, raise --liquidity-native and --liquidity-asset"
+        }
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::metadata;
+
+    // Pallet errors are named and the common ones come with a remediation
+    #[test]
+    fn dispatch_error_classification() {
+        use subxt::error::DispatchError;
+
+        let metadata = metadata();
+        // `DispatchError::Module` is variant 3, with the pallet index and the
+        // error index padded to 4 bytes
+        let classify = |pallet: u8, error: u8| {
+            let err = DispatchError::decode_from(&[3, pallet, error, 0, 0, 0][..], metadata.clone()).unwrap();
+            classify_dispatch_error(&err, &metadata)
+        };
+
+        let in_use = classify(50, 5);
+        assert!(in_use.starts_with("Assets::InUse"), "{in_use}");
+        assert!(in_use.ends_with("pick another one with --asset-id"), "{in_use}");
+        let pool_exists = classify(56, 1);
+        assert!(pool_exists.starts_with("AssetConversion::PoolExists"), "{pool_exists}");
+        let unknown = classify(255, 0);
+        assert!(unknown.starts_with("error 0 of the pallet 255"), "{unknown}");
+    }
+}
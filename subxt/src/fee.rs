@@ -0,0 +1,260 @@
+use std::fmt;
+
+use subxt::{
+    blocks::ExtrinsicEvents,
+    tx::SubmittableExtrinsic,
+    utils::{AccountId32, MultiAddress},
+    OnlineClient,
+};
+
+use subxt_signer::sr25519::dev;
+
+use crate::{
+    amm::spot_price,
+    cli::Priority,
+    convert_fees,
+    error::{check_signed_extensions, AppError},
+    estimate_fees, local,
+    units::format_units,
+    CustomConfig, MultiLocation, TRANSFER_AMOUNT,
+};
+
+// The fee of a transfer in both the native token and the custom asset
+pub struct FeeEstimate {
+    pub asset_id: u32,
+    pub native_fee: u128,
+    pub native_decimals: u8,
+    pub native_symbol: String,
+    pub asset_fee: u128,
+    pub asset_decimals: u8,
+    pub asset_symbol: String,
+}
+
+impl FeeEstimate {
+    // Custom asset units paid per native token, including the pool's fee and
+    // price impact
+    pub fn rate(&self) -> Option<f64> {
+        spot_price(self.native_fee, self.native_decimals, self.asset_fee, self.asset_decimals)
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "asset_id": self.asset_id,
+            "native_fee": self.native_fee.to_string(),
+            "asset_fee": self.asset_fee.to_string(),
+            "native_fee_units": format_units(self.native_fee, self.native_decimals),
+            "asset_fee_units": format_units(self.asset_fee, self.asset_decimals),
+            "native_symbol": self.native_symbol,
+            "asset_symbol": self.asset_symbol,
+            "rate": self.rate(),
+        })
+    }
+}
+
+impl fmt::Display for FeeEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let native = format_units(self.native_fee, self.native_decimals);
+        let asset = format_units(self.asset_fee, self.asset_decimals);
+        write!(f, "The estimated fee is {native} {} = {asset} {}", self.native_symbol, self.asset_symbol)?;
+
+        if let Some(rate) = self.rate() {
+            write!(f, " (1 {} = {rate} {})", self.native_symbol, self.asset_symbol)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Estimates the fee of a native transfer and converts it to the custom asset,
+// along with what's needed to display both amounts
+pub async fn estimate_fee_both_units(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    dest: MultiAddress<AccountId32, ()>,
+    amount: u128,
+    native_token: &(u8, String),
+) -> Result<FeeEstimate, AppError> {
+    let (native_decimals, native_symbol) = native_token;
+
+    let native_fee = estimate_fees(api.clone(), dest, amount).await?;
+    let asset_fee = convert_fees(api.clone(), asset_id, native_fee).await?;
+
+    let metadata = api
+        .storage()
+        .at_latest()
+        .await?
+        .fetch(&local::storage().assets().metadata(asset_id))
+        .await?;
+    let (asset_decimals, asset_symbol) = match metadata {
+        Some(metadata) => (metadata.decimals, String::from_utf8_lossy(&metadata.symbol.0).into_owned()),
+        None => (0, format!("asset {asset_id}")),
+    };
+
+    Ok(FeeEstimate {
+        asset_id,
+        native_fee,
+        native_decimals: *native_decimals,
+        native_symbol: native_symbol.clone(),
+        asset_fee,
+        asset_decimals,
+        asset_symbol,
+    })
+}
+
+// The components of a transaction's fee, as computed by
+// `TransactionPaymentApi_query_fee_details`. Unsigned transactions pay no
+// inclusion fee, hence the `Option`.
+pub struct FeeDetails {
+    pub inclusion_fee: Option<InclusionFee>,
+    pub tip: u128,
+}
+
+pub struct InclusionFee {
+    pub base_fee: u128,
+    pub len_fee: u128,
+    pub adjusted_weight_fee: u128,
+}
+
+impl FeeDetails {
+    pub fn total(&self) -> u128 {
+        let inclusion_fee = self
+            .inclusion_fee
+            .as_ref()
+            .map_or(0, |fee| fee.base_fee.saturating_add(fee.len_fee).saturating_add(fee.adjusted_weight_fee));
+        inclusion_fee.saturating_add(self.tip)
+    }
+
+    // Renders the components line by line, in the native token
+    pub fn breakdown(&self, native_decimals: u8, native_symbol: &str) -> String {
+        let format = |amount| format!("{} {native_symbol}", format_units(amount, native_decimals));
+
+        let mut breakdown = String::from("Fee breakdown:\n");
+        match &self.inclusion_fee {
+            Some(fee) => {
+                breakdown.push_str(&format!("  base fee            {}\n", format(fee.base_fee)));
+                breakdown.push_str(&format!("  length fee          {}\n", format(fee.len_fee)));
+                breakdown.push_str(&format!("  adjusted weight fee {}\n", format(fee.adjusted_weight_fee)));
+            }
+            None => breakdown.push_str("  no inclusion fee (unsigned transaction)\n"),
+        }
+        breakdown.push_str(&format!("  tip                 {}\n", format(self.tip)));
+        breakdown.push_str(&format!("  total               {}\n", format(self.total())));
+
+        breakdown
+    }
+}
+
+// Asks the runtime how the fee of a signed transaction is made up. The fee is
+// computed in the native token; paying it with a custom asset swaps that
+// amount through the pool on top of this.
+pub async fn fee_details(
+    api: &OnlineClient<CustomConfig>,
+    tx: &SubmittableExtrinsic<CustomConfig, OnlineClient<CustomConfig>>,
+) -> Result<FeeDetails, AppError> {
+    let encoded = tx.encoded().to_vec();
+    let len = encoded.len() as u32;
+
+    let query = local::apis().transaction_payment_api().query_fee_details(encoded.into(), len);
+    let details = api.runtime_api().at_latest().await?.call(query).await?;
+
+    Ok(FeeDetails {
+        inclusion_fee: details.inclusion_fee.map(|fee| InclusionFee {
+            base_fee: fee.base_fee,
+            len_fee: fee.len_fee,
+            adjusted_weight_fee: fee.adjusted_weight_fee,
+        }),
+        tip: details.tip,
+    })
+}
+
+// The `fee-breakdown` subcommand: how the fee of the example transfer to Bob
+// is made up, in the native token
+pub async fn print_breakdown(api: &OnlineClient<CustomConfig>, native_token: &(u8, String)) -> Result<(), AppError> {
+    let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+    let transfer = local::tx().balances().transfer_keep_alive(dest, TRANSFER_AMOUNT);
+    // Like `estimate_fee`, the fee doesn't depend on who signs
+    let signed = api
+        .tx()
+        .create_signed(&transfer, &dev::alice(), Default::default())
+        .await
+        .map_err(check_signed_extensions)?;
+    let details = fee_details(api, &signed).await?;
+    print!("{}", details.breakdown(native_token.0, &native_token.1));
+    Ok(())
+}
+
+// How a submitted transaction's fee was actually paid
+#[derive(Debug)]
+pub enum FeePayment {
+    // Through the pool, in the given asset, by `payer`. `amount` includes
+    // the tip, which is `tip` native plancks converted to the asset.
+    Asset {
+        amount: u128,
+        tip: u128,
+        payer: AccountId32,
+        asset_id: Box<MultiLocation>,
+    },
+    // In the native token, e.g. because the asset couldn't be used
+    Native { amount: u128 },
+}
+
+// Tells from a transaction's events which path its fee took: paying with an
+// asset emits `AssetTxFeePaid`, paying natively `TransactionFeePaid`
+pub fn fee_payment(events: &ExtrinsicEvents<CustomConfig>) -> Result<FeePayment, AppError> {
+    if let Some(paid) = events.find_first::<local::asset_tx_payment::events::AssetTxFeePaid>()? {
+        return Ok(FeePayment::Asset {
+            amount: paid.actual_fee,
+            tip: paid.tip,
+            payer: paid.who,
+            asset_id: Box::new(paid.asset_id),
+        });
+    }
+
+    if let Some(paid) = events.find_first::<local::transaction_payment::events::TransactionFeePaid>()? {
+        return Ok(FeePayment::Native {
+            amount: paid.actual_fee,
+        });
+    }
+
+    Err(AppError::UnexpectedOutcome("no fee payment event was emitted".into()))
+}
+
+// Recent blocks whose tips are sampled to price a priority
+const TIP_SAMPLE_BLOCKS: usize = 10;
+
+// Picks a tip (in native plancks) for the priority from the tips paid in the
+// last `TIP_SAMPLE_BLOCKS` finalized blocks: the 25th, 50th or 90th
+// percentile. On a quiet chain with nothing to sample no tip is needed.
+pub async fn tip_for_priority(
+    api: &OnlineClient<CustomConfig>,
+    priority: Priority,
+) -> Result<u128, AppError> {
+    let mut tips = Vec::new();
+    let mut block = api.blocks().at_latest().await?;
+
+    for _ in 0..TIP_SAMPLE_BLOCKS {
+        for ext in block.extrinsics().await?.iter() {
+            if let Some(tip) = ext?.signed_extensions().and_then(|extensions| extensions.tip()) {
+                tips.push(tip);
+            }
+        }
+
+        if block.number() == 0 {
+            break;
+        }
+        block = api.blocks().at(block.header().parent_hash).await?;
+    }
+
+    if tips.is_empty() {
+        return Ok(0);
+    }
+
+    tips.sort_unstable();
+    let percentile = match priority {
+        Priority::Low => 25,
+        Priority::Medium => 50,
+        Priority::High => 90,
+    };
+
+    Ok(tips[(tips.len() - 1) * percentile / 100])
+}
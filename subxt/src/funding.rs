@@ -0,0 +1,66 @@
+use subxt::{tx::Signer, utils::AccountId32, OnlineClient};
+use subxt_signer::sr25519::Keypair;
+use tracing::info;
+
+use crate::{
+    error::AppError,
+    free_balance, local,
+    signer::create_verified,
+    submit_idempotent,
+    units::format_units,
+    CustomConfig, SubmitPolicy,
+};
+
+// Tops the signer up from a separate funding account so that it holds at
+// least `needed`. An extrinsic has a single origin, so the funder can't pay
+// the setup directly: it sends the shortfall in its own transaction first.
+// Fails if the signer still falls short afterwards.
+pub async fn fund_signer(
+    api: &OnlineClient<CustomConfig>,
+    funder: &Keypair,
+    policy: &SubmitPolicy,
+    signer: AccountId32,
+    needed: u128,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    let (native_decimals, native_symbol) = native_token;
+    let funder_account = <Keypair as Signer<CustomConfig>>::account_id(funder);
+
+    let balance = free_balance(api, signer.clone()).await?;
+    if balance < needed {
+        let shortfall = needed - balance;
+        info!(
+            shortfall,
+            funder = %funder_account,
+            display = format!("{} {native_symbol}", format_units(shortfall, *native_decimals)),
+            "Funding the signer"
+        );
+
+        let transfer = local::tx().balances().transfer_keep_alive(signer.clone().into(), shortfall);
+        let signed = create_verified(api, &transfer, funder, Default::default()).await?;
+        submit_idempotent(api, policy, signed).await?;
+    }
+
+    let signer_balance = free_balance(api, signer.clone()).await?;
+    let funder_balance = free_balance(api, funder_account.clone()).await?;
+    info!(
+        %signer,
+        balance = format!("{} {native_symbol}", format_units(signer_balance, *native_decimals)),
+        "Signer balance after funding"
+    );
+    info!(
+        funder = %funder_account,
+        balance = format!("{} {native_symbol}", format_units(funder_balance, *native_decimals)),
+        "Funder balance after funding"
+    );
+
+    if signer_balance < needed {
+        return Err(AppError::Setup(format!(
+            "the signer has {} {native_symbol} after funding but the setup needs {} {native_symbol}",
+            format_units(signer_balance, *native_decimals),
+            format_units(needed, *native_decimals)
+        )));
+    }
+
+    Ok(())
+}
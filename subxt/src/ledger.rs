@@ -0,0 +1,135 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use subxt::{
+    tx::Signer,
+    utils::{AccountId32, MultiAddress, MultiSignature},
+};
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tracing::error;
+
+use crate::{error::AppError, CustomConfig};
+
+// A `Signer` that never sees the seed: the key lives on a Ledger device and
+// every signature is requested from an external signing command that talks to
+// it (e.g. a small wrapper around the Polkadot Ledger app).
+//
+// The command is invoked in two ways:
+//
+// - `<command> address` must print the account's ed25519 public key as hex
+//   (32 bytes, `0x` prefix optional). It's called once when the signer is
+//   created.
+// - `<command> sign` receives the signer payload as a single line of hex on
+//   stdin and must print the ed25519 signature as hex (64 bytes).
+//
+// The signer payload is exactly what subxt signs for any other signer, so the
+// device can decode and display it before approving:
+//
+//   call_data ++ extra ++ additional_signed
+//
+// where `extra` is the SCALE encoded signed extension data (era, nonce, tip
+// and the `ChargeAssetTxPayment` asset id) and `additional_signed` is the spec
+// version, tx version, genesis hash and mortality checkpoint. If that payload
+// is longer than 256 bytes it's replaced by its blake2-256 hash, as required
+// by Substrate, so the device receives 32 bytes instead.
+#[derive(Clone, Debug)]
+pub struct LedgerSigner {
+    command: String,
+    account_id: AccountId32,
+}
+
+impl LedgerSigner {
+    // Asks the device for its public key and keeps it around, since subxt
+    // needs the account id several times per transaction.
+    pub fn new(command: &str) -> Result<Self, AppError> {
+        let output = Command::new(command)
+            .arg("address")
+            .output()
+            .map_err(|e| AppError::Signer(format!("could not run the Ledger command `{command} address`: {e}")))?;
+        if !output.status.success() {
+            return Err(AppError::Signer(format!(
+                "the Ledger command `{command} address` failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let public_key: [u8; 32] = decode_hex(&output.stdout)?
+            .try_into()
+            .map_err(|_| AppError::Signer("the Ledger public key must be 32 bytes".into()))?;
+
+        Ok(LedgerSigner {
+            command: command.to_string(),
+            account_id: AccountId32(public_key),
+        })
+    }
+
+    fn request_signature(&self, payload: &[u8]) -> Result<[u8; 64], AppError> {
+        let command_error = |e: std::io::Error| AppError::Signer(format!("the Ledger command `{} sign` failed: {e}", self.command));
+        let mut child = Command::new(&self.command)
+            .arg("sign")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(command_error)?;
+
+        child
+            .stdin
+            .take()
+            .ok_or_else(|| AppError::Signer("could not open the Ledger command's stdin".into()))?
+            .write_all(format!("0x{}\n", hex::encode(payload)).as_bytes())
+            .map_err(command_error)?;
+
+        let output = child.wait_with_output().map_err(command_error)?;
+        if !output.status.success() {
+            return Err(AppError::Signer(format!(
+                "the Ledger command `{} sign` failed: {}",
+                self.command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let signature = decode_hex(&output.stdout)?
+            .try_into()
+            .map_err(|_| AppError::Signer("the Ledger signature must be 64 bytes".into()))?;
+
+        Ok(signature)
+    }
+}
+
+impl Signer<CustomConfig> for LedgerSigner {
+    fn account_id(&self) -> AccountId32 {
+        self.account_id.clone()
+    }
+
+    fn address(&self) -> MultiAddress<AccountId32, ()> {
+        self.account_id.clone().into()
+    }
+
+    // `Signer::sign` can't return an error, so a rejected or failed signature
+    // on the device is logged and comes back as an all zero signature, which
+    // `create_verified` turns into an `AppError::Signer`. Waiting for the
+    // device blocks, so on a multi-threaded runtime the other tasks are moved
+    // off this thread in the meantime.
+    fn sign(&self, signer_payload: &[u8]) -> MultiSignature {
+        let request = || self.request_signature(signer_payload);
+        let result = match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(request),
+            _ => request(),
+        };
+        match result {
+            Ok(signature) => MultiSignature::Ed25519(signature),
+            Err(e) => {
+                error!("Could not sign with the Ledger device: {e}");
+                MultiSignature::Ed25519([0; 64])
+            }
+        }
+    }
+}
+
+fn decode_hex(bytes: &[u8]) -> Result<Vec<u8>, AppError> {
+    let text = String::from_utf8_lossy(bytes);
+    let text = text.trim();
+    hex::decode(text.strip_prefix("0x").unwrap_or(text))
+        .map_err(|e| AppError::Signer(format!("the Ledger command printed invalid hex: {e}")))
+}
@@ -0,0 +1,2126 @@
+use std::{str::FromStr, time::Duration};
+
+use codec::{Decode, Encode};
+use subxt::{
+    OnlineClient,
+    blocks::{Block, ExtrinsicEvents},
+    events::EventDetails,
+    storage::Storage,
+    tx::{Payload, Signer, SubmittableExtrinsic, ValidationResult},
+    config::{
+        DefaultExtrinsicParamsBuilder,
+        DefaultExtrinsicParams,
+        ExtrinsicParams,
+        signed_extensions::ChargeAssetTxPaymentParams,
+        Config,
+        Hasher,
+        PolkadotConfig, 
+        SubstrateConfig, 
+        }, 
+        utils::{
+            AccountId32, MultiAddress, H256
+        }
+    };
+use subxt_signer::{sr25519::{dev::{self}, Keypair}, SecretUri};
+use tracing::{debug, info, warn};
+
+mod admin;
+mod amm;
+pub mod cli;
+pub mod client;
+mod commands;
+mod compare;
+mod cost;
+mod describe;
+mod dynamic;
+pub mod error;
+mod fee;
+mod funding;
+pub mod ledger;
+#[cfg(feature = "light-client")]
+pub mod light_client;
+mod liquidity;
+mod list;
+pub mod location;
+pub mod query;
+pub mod reconnect;
+mod replay;
+mod rollback;
+mod route;
+mod setup;
+pub mod signer;
+mod status;
+mod swap;
+pub mod units;
+mod watch;
+mod whoami;
+
+use cli::{AssetSpec, BatchMode, Cli, Command};
+pub use client::{AssetHubClient, BorrowedSigner, QuoteKind};
+pub use location::AssetLocation;
+pub use fee::FeePayment;
+pub use setup::setup;
+use reconnect::{is_transport_error, ReconnectingClient, RETRY_BACKOFF};
+use compare::compare_endpoints;
+use error::{check_signed_extensions, AppError};
+use fee::{estimate_fee_both_units, fee_payment, tip_for_priority};
+use replay::replay;
+use signer::create_verified;
+use route::compare_path_quote;
+use units::format_units;
+use whoami::whoami;
+
+// Metadata that we'll use for our example. The runtime it was fetched from
+// locates assets with XCM v3 `MultiLocation`s, newer ones take v4
+// `Location`s, see `location`.
+#[subxt::subxt(runtime_metadata_path = "./metadata/asset_hub_metadata.scale",
+derive_for_type(
+    path = "staging_xcm::v3::multilocation::MultiLocation",
+    derive = "Clone",
+    recursive
+),
+derive_for_type(
+    path = "asset_hub_westend_runtime::RuntimeCall",
+    derive = "Clone",
+    recursive
+))]
+pub mod local {}
+
+// Types that we retrieve from the Metadata for our example
+pub type MultiLocation = local::runtime_types::staging_xcm::v3::multilocation::MultiLocation;
+
+pub type Call = local::runtime_types::asset_hub_westend_runtime::RuntimeCall;
+type AssetConversionCall = local::asset_conversion::Call;
+type AssetsCall = local::assets::Call;
+type BalancesCall = local::balances::Call;
+type UtilityCall = local::utility::Call;
+
+// Defaults of the command line options: the asset the setup creates (see
+// `--asset-id`, `--name` and `--symbol`) and the node it connects to
+const ASSET_ID: u32 = 1;
+const NAME: &str = "Testy";
+const SYMBOL: &str = "TSTY";
+const URI: &str = "ws://127.0.0.1:9944";
+// Index of `pallet-assets` in the Asset Hub Westend runtime, the pallet
+// instance in the locations of its assets
+const ASSETS_PALLET_INSTANCE: u8 = 50;
+// Native amount sent to Bob in the example transfer
+const TRANSFER_AMOUNT: u128 = 100000;
+
+// Submission retries. A submission that isn't finalized within
+// `SUBMIT_TIMEOUT`, or fails transiently, is retried up to `SUBMIT_RETRIES`
+// times (`--max-submit-retries`), but only after checking that the exact
+// same extrinsic isn't already in one of the last `IDEMPOTENCY_WINDOW`
+// finalized blocks (~2 minutes with 6s blocks).
+const SUBMIT_RETRIES: usize = 3;
+const SUBMIT_TIMEOUT: Duration = Duration::from_secs(120);
+const IDEMPOTENCY_WINDOW: usize = 20;
+// Time to wait for the pool's liquidity to be visible after the setup
+const POOL_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+// Amounts of the price curve, each ten times the previous one
+const CURVE_STEPS: u32 = 6;
+// Slippage accepted on swaps below the quoted output, in per mille
+const SWAP_SLIPPAGE: u128 = 10;
+// Margin, in per mille, added on top of the minimal liquidity
+const LIQUIDITY_BUFFER: u128 = 100;
+// Margin, in percent, added on top of the fee estimated in the asset
+pub const FEE_BUFFER_PERCENT: u8 = 10;
+// Default of `--max-batch-len`, the most mints `mint_to_many` puts in a batch
+pub const MAX_BATCH_LEN: usize = 100;
+// Default amount of the `price-curve` and `quote-path` subcommands
+pub const QUOTE_AMOUNT: u128 = 1_000_000_000;
+
+// This is our custom configuration for the signed extensions.
+// We don't need to construct this at runtime,
+// so an empty enum is appropriate:
+pub enum CustomConfig {}
+
+// Signed extensions of Asset Hub Westend (formerly Westmint), as configured
+// by `CustomConfig`
+pub type WestmintExtrinsicParams = DefaultExtrinsicParams<CustomConfig>;
+pub type WestmintExtrinsicParamsBuilder = DefaultExtrinsicParamsBuilder<CustomConfig>;
+
+// Tip of a transaction, optionally paid in an asset (given by its location)
+// through `ChargeAssetTxPayment`
+pub type AssetTip = ChargeAssetTxPaymentParams<CustomConfig>;
+
+// Which token a transaction's fee is paid in: the native one, or an asset
+// swapped to it through its pool with the native token
+#[derive(Clone, Debug)]
+pub enum FeeMode {
+    Native,
+    Asset(Box<MultiLocation>),
+}
+
+// How many blocks a transaction stays valid for after the block it was signed
+// at, instead of forever. The runtime only knows eras whose period is a power
+// of two between 4 and 65536 blocks, anything else would be silently rounded
+// by subxt, so the period is rounded up here where it can be logged, and
+// periods that can't be represented are rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MortalityConfig {
+    pub period_blocks: u64,
+}
+
+impl MortalityConfig {
+    pub const MIN_PERIOD: u64 = 4;
+    pub const MAX_PERIOD: u64 = 1 << 16;
+
+    pub fn new(period_blocks: u64) -> Result<Self, String> {
+        if period_blocks == 0 || period_blocks > Self::MAX_PERIOD {
+            return Err(format!(
+                "a mortal period has to be between 1 and {} blocks, got {period_blocks}",
+                Self::MAX_PERIOD
+            ));
+        }
+
+        let period_blocks = period_blocks.next_power_of_two().max(Self::MIN_PERIOD);
+        Ok(Self { period_blocks })
+    }
+}
+
+// Starts the signed extensions of a transaction, mortal from the latest block
+// when `mortality` is set and immortal otherwise
+async fn params_builder(
+    api: &OnlineClient<CustomConfig>,
+    mortality: Option<MortalityConfig>,
+) -> Result<WestmintExtrinsicParamsBuilder, subxt::Error> {
+    let builder = WestmintExtrinsicParamsBuilder::new();
+    let Some(MortalityConfig { period_blocks }) = mortality else {
+        return Ok(builder);
+    };
+
+    let header = api.blocks().at_latest().await?.header().clone();
+    debug!(from = header.number, period_blocks, "Signing a mortal transaction");
+    Ok(builder.mortal(&header, period_blocks))
+}
+
+impl Config for CustomConfig {
+    type Hash = <SubstrateConfig as Config>::Hash;
+    type AccountId = <SubstrateConfig as Config>::AccountId;
+    type Address = <PolkadotConfig as Config>::Address;
+    type Signature = <SubstrateConfig as Config>::Signature;
+    type Hasher = <SubstrateConfig as Config>::Hasher;
+    type Header = <SubstrateConfig as Config>::Header;
+    type ExtrinsicParams = WestmintExtrinsicParams;
+    type AssetId = MultiLocation;
+
+}
+
+// Keypair from a secret URI: a mnemonic phrase or a `0x` hex seed, followed
+// by optional derivation junctions and password, e.g. `//Alice` or
+// `<mnemonic>//hard/soft///password`
+pub fn signer_from_secret_uri(uri: &str) -> Result<Keypair, AppError> {
+    let uri = SecretUri::from_str(uri).map_err(|e| AppError::Signer(format!("invalid secret URI: {e}")))?;
+    Keypair::from_uri(&uri).map_err(|e| AppError::Signer(format!("invalid secret URI: {e}")))
+}
+
+// Location of the relay chain's native token, as seen from Asset Hub, in
+// the XCM version asked for, see `AssetLocation`
+pub fn native_location<L: AssetLocation>() -> L {
+    L::native()
+}
+
+// Location of an asset of `pallet-assets`, under its pallet instance
+pub fn local_asset_location<L: AssetLocation>(asset_id: u32) -> L {
+    L::local_asset(asset_id)
+}
+
+// Id of the `pallet-assets` asset at `location`, the reverse of
+// `local_asset_location`
+pub fn local_asset_id<L: AssetLocation>(location: &L) -> Option<u32> {
+    location.local_asset_id()
+}
+
+// `pallet-assets` create_asset call
+pub fn create_asset_call(
+    asset_id: u32,
+    admin: MultiAddress<AccountId32, ()>,
+    min_balance: u128,
+) -> Call {
+    Call::Assets(AssetsCall::create {
+        id: asset_id,
+        admin,
+        min_balance,
+    })
+}
+
+// `pallet-assets` set_metadata call
+pub fn set_asset_metadata_call(
+    asset_id: u32,
+    name: Vec<u8>,
+    symbol: Vec<u8>,
+    decimals: u8,
+) -> Call {
+    Call::Assets(AssetsCall::set_metadata {
+        id: asset_id,
+        name,
+        symbol,
+        decimals,
+    })
+}
+
+// `pallet-assets` create_mint call
+pub fn mint_token_call(
+    asset_id: u32,
+    beneficiary: MultiAddress<AccountId32, ()>,
+    amount: u128,
+) -> Call {
+    Call::Assets(AssetsCall::mint {
+        id: asset_id,
+        beneficiary,
+        amount,
+    })
+}
+
+// One `pallet-assets` mint per beneficiary, to submit in a single batch
+pub fn mint_batch_calls(asset_id: u32, beneficiaries: &[(MultiAddress<AccountId32, ()>, u128)]) -> Vec<Call> {
+    beneficiaries
+        .iter()
+        .map(|(beneficiary, amount)| mint_token_call(asset_id, beneficiary.clone(), *amount))
+        .collect()
+}
+
+// `pallet-assets` burn call, destroying `amount` of the asset held by `who`
+pub fn burn_token_call(
+    asset_id: u32,
+    who: MultiAddress<AccountId32, ()>,
+    amount: u128,
+) -> Call {
+    Call::Assets(AssetsCall::burn {
+        id: asset_id,
+        who,
+        amount,
+    })
+}
+
+// `pallet-assets` transfer_keep_alive call, moving `amount` of the asset from
+// the signer to `target` without reaping the signer's account of it
+pub fn transfer_asset_call(
+    asset_id: u32,
+    target: MultiAddress<AccountId32, ()>,
+    amount: u128,
+) -> Call {
+    Call::Assets(AssetsCall::transfer_keep_alive {
+        id: asset_id,
+        target,
+        amount,
+    })
+}
+
+// `pallet-assets` touch_other call, creating `who`'s account of the asset with
+// a deposit from the signer, so that it can receive the asset whether or not
+// it's sufficient. The signer has to be the asset's freezer or admin.
+pub fn touch_other_call(asset_id: u32, who: MultiAddress<AccountId32, ()>) -> Call {
+    Call::Assets(AssetsCall::touch_other { id: asset_id, who })
+}
+
+// `pallet-assets` freeze_asset call, stopping every transfer of the asset
+pub fn freeze_asset_call(asset_id: u32) -> Call {
+    Call::Assets(AssetsCall::freeze_asset { id: asset_id })
+}
+
+// `pallet-assets` thaw_asset call, undoing `freeze_asset_call`
+pub fn thaw_asset_call(asset_id: u32) -> Call {
+    Call::Assets(AssetsCall::thaw_asset { id: asset_id })
+}
+
+// `pallet-assets` freeze call, stopping `who` from moving its balance of the
+// asset
+pub fn freeze_account_call(asset_id: u32, who: MultiAddress<AccountId32, ()>) -> Call {
+    Call::Assets(AssetsCall::freeze { id: asset_id, who })
+}
+
+// `pallet-assets` thaw call, undoing `freeze_account_call`
+pub fn thaw_account_call(asset_id: u32, who: MultiAddress<AccountId32, ()>) -> Call {
+    Call::Assets(AssetsCall::thaw { id: asset_id, who })
+}
+
+// `pallet-assets` set_min_balance call. The minimum balance can only be
+// raised while no account holds the asset.
+pub fn set_min_balance_call(asset_id: u32, min_balance: u128) -> Call {
+    Call::Assets(AssetsCall::set_min_balance { id: asset_id, min_balance })
+}
+
+// `pallet-assets` clear_metadata call, returning the metadata deposit to the
+// owner
+pub fn clear_metadata_call(asset_id: u32) -> Call {
+    Call::Assets(AssetsCall::clear_metadata { id: asset_id })
+}
+
+// `pallet-assets` transfer_ownership call
+pub fn transfer_ownership_call(
+    asset_id: u32,
+    owner: MultiAddress<AccountId32, ()>,
+) -> Call {
+    Call::Assets(AssetsCall::transfer_ownership {
+        id: asset_id,
+        owner,
+    })
+}
+
+// `pallet-assets` set_team call
+pub fn set_team_call(
+    asset_id: u32,
+    issuer: MultiAddress<AccountId32, ()>,
+    admin: MultiAddress<AccountId32, ()>,
+    freezer: MultiAddress<AccountId32, ()>,
+) -> Call {
+    Call::Assets(AssetsCall::set_team {
+        id: asset_id,
+        issuer,
+        admin,
+        freezer,
+    })
+}
+
+// We will use this to create the liquidity pool with a Native asset and our Custom asset
+pub fn create_pool_with_native_call(asset_id: u32) -> Call {
+    create_pool_call(native_location(), local_asset_location(asset_id))
+}
+
+// `pallet-asset-conversion` create_pool call, for a pool between any two
+// assets
+pub fn create_pool_call(asset1: MultiLocation, asset2: MultiLocation) -> Call {
+    Call::AssetConversion(AssetConversionCall::create_pool {
+        asset1: Box::new(asset1),
+        asset2: Box::new(asset2),
+    })
+}
+
+// We will use this to add liquidity to our liquidity pool
+pub fn provide_liquidity_to_token_native_pool_call(
+    asset_id: u32,
+    amount1_desired: u128,
+    amount2_desired: u128,
+    amount1_min: u128,
+    amount2_min: u128,
+    mint_to: AccountId32,
+) -> Call {
+    add_liquidity_call(
+        native_location(),
+        local_asset_location(asset_id),
+        (amount1_desired, amount2_desired),
+        (amount1_min, amount2_min),
+        mint_to,
+    )
+}
+
+// `pallet-asset-conversion` add_liquidity call to the pool between any two
+// assets, the amounts being given in the order of the assets
+pub fn add_liquidity_call(
+    asset1: MultiLocation,
+    asset2: MultiLocation,
+    (amount1_desired, amount2_desired): (u128, u128),
+    (amount1_min, amount2_min): (u128, u128),
+    mint_to: AccountId32,
+) -> Call {
+    Call::AssetConversion(AssetConversionCall::add_liquidity {
+        asset1: Box::new(asset1),
+        asset2: Box::new(asset2),
+        amount1_desired,
+        amount2_desired,
+        amount1_min,
+        amount2_min,
+        mint_to,
+    })
+}
+
+// Locations of two local assets in the order of their pool's key in
+// `AssetConversion::Pools`, which sorts them by id
+fn asset_pair(asset1: u32, asset2: u32) -> (MultiLocation, MultiLocation) {
+    (local_asset_location(asset1.min(asset2)), local_asset_location(asset1.max(asset2)))
+}
+
+// `pallet-asset-conversion` remove_liquidity call, burning `lp_token_burn`
+// LP tokens of the pool between the two assets
+pub fn remove_liquidity_call(
+    asset1: MultiLocation,
+    asset2: MultiLocation,
+    lp_token_burn: u128,
+    amount1_min_receive: u128,
+    amount2_min_receive: u128,
+    withdraw_to: AccountId32,
+) -> Call {
+    Call::AssetConversion(AssetConversionCall::remove_liquidity {
+        asset1: Box::new(asset1),
+        asset2: Box::new(asset2),
+        lp_token_burn,
+        amount1_min_receive,
+        amount2_min_receive,
+        withdraw_to,
+    })
+}
+
+// `pallet-asset-conversion` swap_exact_tokens_for_tokens call. With
+// `keep_alive` the swap fails rather than reaping the signer's account.
+pub fn swap_exact_tokens_for_tokens_call(
+    path: Vec<MultiLocation>,
+    amount_in: u128,
+    amount_out_min: u128,
+    send_to: AccountId32,
+    keep_alive: bool,
+) -> Call {
+    Call::AssetConversion(AssetConversionCall::swap_exact_tokens_for_tokens {
+        path: Box::new(path),
+        amount_in,
+        amount_out_min,
+        send_to,
+        keep_alive,
+    })
+}
+
+// `pallet-asset-conversion` swap_tokens_for_exact_tokens call, the exact
+// output counterpart of `swap_exact_tokens_for_tokens_call`
+pub fn swap_tokens_for_exact_tokens_call(
+    path: Vec<MultiLocation>,
+    amount_out: u128,
+    amount_in_max: u128,
+    send_to: AccountId32,
+    keep_alive: bool,
+) -> Call {
+    Call::AssetConversion(AssetConversionCall::swap_tokens_for_exact_tokens {
+        path: Box::new(path),
+        amount_out,
+        amount_in_max,
+        send_to,
+        keep_alive,
+    })
+}
+
+// All the events of type `E` a transaction emitted, in order
+fn find_events<E: subxt::events::StaticEvent>(
+    events: &ExtrinsicEvents<CustomConfig>,
+) -> Result<Vec<E>, subxt::Error> {
+    events.find::<E>().collect()
+}
+
+// Hash of an extrinsic from its bytes in a block body, which are the whole
+// encoded extrinsic, length prefix included, as the submitted one is hashed.
+// Hashing them with `hash_of` would encode them again behind another prefix.
+fn extrinsic_hash(bytes: &[u8]) -> <CustomConfig as Config>::Hash {
+    <CustomConfig as Config>::Hasher::hash(bytes)
+}
+
+// Looks for an extrinsic with the given hash in a block and returns its
+// events if it's there
+async fn find_in_block(
+    block: &Block<CustomConfig, OnlineClient<CustomConfig>>,
+    tx_hash: <CustomConfig as Config>::Hash,
+) -> Result<Option<SubmissionReceipt>, AppError> {
+    for ext in block.extrinsics().await?.iter() {
+        let ext = ext?;
+        if extrinsic_hash(ext.bytes()) == tx_hash {
+            let events = ext.events().await?;
+            if let Some(failed) = events.find_first::<local::system::events::ExtrinsicFailed>()? {
+                return Err(AppError::UnexpectedOutcome(format!(
+                    "extrinsic {tx_hash:?} failed: {:?}",
+                    failed.dispatch_error
+                )));
+            }
+            return Ok(Some(SubmissionReceipt { block_hash: block.hash(), extrinsic_hash: tx_hash, events }));
+        }
+    }
+
+    Ok(None)
+}
+
+// Looks for an extrinsic with the given hash in the last `IDEMPOTENCY_WINDOW`
+// finalized blocks and returns its events if it's there
+async fn find_in_recent_blocks(
+    api: &OnlineClient<CustomConfig>,
+    tx_hash: <CustomConfig as Config>::Hash,
+) -> Result<Option<SubmissionReceipt>, AppError> {
+    let mut block = api.blocks().at_latest().await?;
+
+    for _ in 0..IDEMPOTENCY_WINDOW {
+        if let Some(receipt) = find_in_block(&block, tx_hash).await? {
+            return Ok(Some(receipt));
+        }
+
+        if block.number() == 0 {
+            break;
+        }
+        block = api.blocks().at(block.header().parent_hash).await?;
+    }
+
+    Ok(None)
+}
+
+// Follows the finalized blocks until one includes the extrinsic with the
+// given hash, for an extrinsic the pool already holds
+async fn wait_for_inclusion(
+    api: &OnlineClient<CustomConfig>,
+    tx_hash: <CustomConfig as Config>::Hash,
+) -> Result<SubmissionReceipt, AppError> {
+    let mut blocks = api.blocks().subscribe_finalized().await?;
+
+    while let Some(block) = blocks.next().await {
+        if let Some(receipt) = find_in_block(&block?, tx_hash).await? {
+            return Ok(receipt);
+        }
+    }
+
+    Err(AppError::Setup("the finalized block subscription ended".into()))
+}
+
+// Where a finalized extrinsic ended up, to look it up in a block explorer or
+// correlate it with the node's logs, along with the events it emitted
+pub struct SubmissionReceipt {
+    pub block_hash: H256,
+    pub extrinsic_hash: H256,
+    pub events: ExtrinsicEvents<CustomConfig>,
+}
+
+impl std::fmt::Display for SubmissionReceipt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "extrinsic {:?} in block {:?}", self.extrinsic_hash, self.block_hash)
+    }
+}
+
+// Prints every event a transaction emitted by its pallet and variant name, and
+// what `AssetTxFeePaid` charged. An event that can't be decoded gets a warning
+// line and the rest are still printed.
+pub fn log_tx_events(events: &ExtrinsicEvents<CustomConfig>) -> Result<(), AppError> {
+    info!(extrinsic = ?events.extrinsic_hash(), "Events of the extrinsic");
+    for (index, event) in events.iter().enumerate() {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                warn!(index, "Could not decode the event: {e}");
+                continue;
+            }
+        };
+
+        match event.as_event::<local::asset_tx_payment::events::AssetTxFeePaid>() {
+            Ok(Some(paid)) => info!(
+                pallet = event.pallet_name(),
+                variant = event.variant_name(),
+                fee = paid.actual_fee,
+                tip = paid.tip,
+                asset = ?paid.asset_id,
+                "Event"
+            ),
+            Ok(None) => info!(pallet = event.pallet_name(), variant = event.variant_name(), "Event"),
+            Err(e) => warn!(
+                pallet = event.pallet_name(),
+                variant = event.variant_name(),
+                "Could not decode the event: {e}"
+            ),
+        }
+    }
+
+    Ok(())
+}
+
+// What the run allows submissions to do: how many times each one is retried,
+// `SUBMIT_RETRIES` by default, and whether they're refused altogether, e.g.
+// on a live chain, with the reason they are. Reads, quotes and dry runs
+// don't go through it.
+#[derive(Clone, Debug)]
+pub struct SubmitPolicy {
+    pub max_retries: usize,
+    pub refused: Option<String>,
+}
+
+impl Default for SubmitPolicy {
+    fn default() -> Self {
+        Self { max_retries: SUBMIT_RETRIES, refused: None }
+    }
+}
+
+impl SubmitPolicy {
+    // Fails with `AppError::Refused` when submissions are refused
+    pub fn check(&self) -> Result<(), AppError> {
+        match &self.refused {
+            Some(reason) => Err(AppError::Refused(reason.clone())),
+            None => Ok(()),
+        }
+    }
+}
+
+// Whether submitting the same extrinsic again can get past the failure: the
+// connection dropped, the pool was full (`Immediately Dropped`), had it
+// temporarily banned, or dropped it later on. The pool rejecting the
+// extrinsic itself (invalid, bad signature, too low a priority...) is
+// permanent, as is anything the runtime did with it once included. The pool
+// already holding it isn't a failure, see `is_already_imported`.
+fn is_transient_submit_error(error: &subxt::Error) -> bool {
+    const PERMANENT: [&str; 4] = [
+        "Invalid Transaction",
+        "Unknown Transaction Validity",
+        "Transaction Already Imported",
+        "Priority is too low",
+    ];
+
+    match error {
+        subxt::Error::Transaction(subxt::error::TransactionError::Dropped(_)) => true,
+        error if is_transport_error(error) => {
+            let message = error.to_string();
+            !PERMANENT.iter().any(|permanent| message.contains(permanent))
+        }
+        _ => false,
+    }
+}
+
+// Whether the pool refused the extrinsic because it already holds it, as
+// after a submission that timed out: the extrinsic is still in flight.
+fn is_already_imported(error: &subxt::Error) -> bool {
+    is_transport_error(error) && error.to_string().contains("Transaction Already Imported")
+}
+
+// Submits an already signed extrinsic and waits for it to be finalized,
+// retrying on timeouts and transient failures, the latter after a backoff
+// doubling each time. The hash is captured before the first attempt, and
+// every retry first checks whether that exact extrinsic already made it into
+// a recent block, so a slow finalization never results in a double submission.
+async fn submit_idempotent(
+    api: &OnlineClient<CustomConfig>,
+    policy: &SubmitPolicy,
+    tx: SubmittableExtrinsic<CustomConfig, OnlineClient<CustomConfig>>,
+) -> Result<SubmissionReceipt, AppError> {
+    submit_reconnecting(&ReconnectingClient::from_client(api.clone()), policy, tx).await
+}
+
+// Same as `submit_idempotent` on a client that can connect again: a retry
+// after losing the connection reconnects first, then submits the same signed
+// bytes on the new connection. The retries are only counted here, the client
+// doesn't retry on its own.
+pub(crate) async fn submit_reconnecting(
+    client: &ReconnectingClient,
+    policy: &SubmitPolicy,
+    tx: SubmittableExtrinsic<CustomConfig, OnlineClient<CustomConfig>>,
+) -> Result<SubmissionReceipt, AppError> {
+    policy.check()?;
+    let tx_hash = tx.hash();
+    let bytes = tx.into_encoded();
+    let retries = policy.max_retries;
+    let mut backoff = RETRY_BACKOFF;
+
+    for attempt in 0..=retries {
+        let api = client.api();
+        if attempt > 0 {
+            if let Some(receipt) = find_in_recent_blocks(&api, tx_hash).await? {
+                info!(extrinsic = ?tx_hash, "The extrinsic was already included, not resubmitting");
+                return Ok(receipt);
+            }
+            warn!(extrinsic = ?tx_hash, attempt, retries, "Resubmitting the extrinsic");
+        }
+
+        let tx = SubmittableExtrinsic::from_bytes(api, bytes.clone());
+        let submission = async {
+            let in_block = tx.submit_and_watch().await?.wait_for_finalized().await?;
+            let block_hash = in_block.block_hash();
+            let events = in_block.wait_for_success().await?;
+            Ok::<_, subxt::Error>(SubmissionReceipt { block_hash, extrinsic_hash: events.extrinsic_hash(), events })
+        };
+
+        match tokio::time::timeout(SUBMIT_TIMEOUT, submission).await {
+            // Resubmitting found the earlier submission still in the pool, so
+            // it's watched for instead, by its hash
+            Ok(Err(e)) if is_already_imported(&e) => {
+                info!(extrinsic = ?tx_hash, "The extrinsic is already in the pool, waiting for its inclusion");
+                match tokio::time::timeout(SUBMIT_TIMEOUT, wait_for_inclusion(&client.api(), tx_hash)).await {
+                    Ok(result) => return result,
+                    Err(_) => warn!(extrinsic = ?tx_hash, "Timed out waiting for the extrinsic to be finalized"),
+                }
+            }
+            Ok(Err(e)) if attempt < retries && is_transient_submit_error(&e) => {
+                warn!(extrinsic = ?tx_hash, ?backoff, "The submission failed transiently: {e}");
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                if is_transport_error(&e) {
+                    client.reconnect().await;
+                }
+            }
+            Ok(result) => return Ok(result?),
+            Err(_) => warn!(extrinsic = ?tx_hash, "Timed out waiting for the extrinsic to be finalized"),
+        }
+    }
+
+    Err(AppError::Timeout(format!(
+        "extrinsic {tx_hash:?} was not finalized after {retries} retries"
+    )))
+}
+
+// A call submitted on its own rather than wrapped in a batch. A `Call` is
+// the runtime's own call type, so it's encoded as it is.
+pub struct CallPayload(pub Call);
+
+impl Payload for CallPayload {
+    fn encode_call_data_to(&self, _metadata: &subxt::Metadata, out: &mut Vec<u8>) -> Result<(), subxt::ext::subxt_core::Error> {
+        self.0.encode_to(out);
+        Ok(())
+    }
+}
+
+// Blake2-256 of the SCALE encoded call, the call hash block explorers show
+fn call_hash(call: &Call) -> <CustomConfig as Config>::Hash {
+    <CustomConfig as Config>::Hasher::hash(&call.encode())
+}
+
+// We use this to sign and send the calls that we defined earlier as a single 
+// batch, tipping `tip` native plancks, and wait until it's successful. With
+// `BatchMode::BestEffort` the calls that fail don't revert the others, and are
+// reported from the batch's events instead of failing the transaction.
+pub async fn sign_and_send_batch_calls<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    calls: Vec<Call>,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    batch_mode: BatchMode,
+) -> Result<SubmissionReceipt, AppError> {
+    let tx_config = params_builder(&api, mortality).await?.tip(tip).build();
+
+    let (batch_hash, signed) = match batch_mode {
+        BatchMode::All => (
+            call_hash(&Call::Utility(UtilityCall::batch_all { calls: calls.clone() })),
+            create_verified(&api, &local::tx().utility().batch_all(calls), signer, tx_config).await,
+        ),
+        BatchMode::BestEffort => (
+            call_hash(&Call::Utility(UtilityCall::force_batch { calls: calls.clone() })),
+            create_verified(&api, &local::tx().utility().force_batch(calls), signer, tx_config).await,
+        ),
+    };
+    let signed = signed?;
+    info!(extrinsic = ?signed.hash(), call_hash = ?batch_hash, ?batch_mode, "Submitting the batch");
+
+    let receipt = submit_idempotent(&api, policy, signed).await?;
+    if let Err(e) = log_tx_events(&receipt.events) {
+        warn!("Could not log the events: {e}");
+    }
+    if batch_mode == BatchMode::BestEffort {
+        for (index, error) in batch_failures(receipt.events.iter())? {
+            warn!(index, "Call {index} of the batch failed: {error}");
+        }
+    }
+    Ok(receipt)
+}
+
+// Index and error of the calls of a batch that failed, from the `ItemFailed`
+// events `force_batch` emits in the order of the calls, or the
+// `BatchInterrupted` event of a `batch` stopping at its failed call. Takes
+// the events of the extrinsic as `ExtrinsicEvents::iter` yields them.
+fn batch_failures<E>(
+    events: impl IntoIterator<Item = Result<EventDetails<CustomConfig>, E>>,
+) -> Result<Vec<(usize, String)>, subxt::Error>
+where
+    subxt::Error: From<E>,
+{
+    use local::utility::events::{BatchInterrupted, ItemCompleted, ItemFailed};
+
+    let mut failures = Vec::new();
+    let mut index = 0;
+    for event in events {
+        let event = event?;
+        if event.as_event::<ItemCompleted>()?.is_some() {
+            index += 1;
+        } else if let Some(ItemFailed { error }) = event.as_event::<ItemFailed>()? {
+            failures.push((index, format!("{error:?}")));
+            index += 1;
+        } else if let Some(BatchInterrupted { index, error }) = event.as_event::<BatchInterrupted>()? {
+            failures.push((index as usize, format!("{error:?}")));
+        }
+    }
+
+    Ok(failures)
+}
+
+// Checks with the `TaggedTransactionQueue_validate_transaction` runtime API
+// that the transaction would be accepted, without submitting it. That covers
+// the signature, the nonce, paying the fee and the other signed extensions,
+// but not what the call does once dispatched.
+pub async fn dry_run<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    call: &impl Payload,
+    tx_config: <WestmintExtrinsicParams as ExtrinsicParams<CustomConfig>>::Params,
+) -> Result<(), AppError> {
+    let signed = create_verified(api, call, signer, tx_config).await?;
+
+    match signed.validate().await? {
+        ValidationResult::Valid(valid) => {
+            info!(
+                extrinsic = ?signed.hash(),
+                priority = valid.priority,
+                longevity = valid.longevity,
+                "Dry run: the extrinsic would be accepted"
+            );
+            Ok(())
+        }
+        ValidationResult::Invalid(invalid) => Err(AppError::Invalid(format!("{invalid:?}"))),
+        ValidationResult::Unknown(unknown) => Err(AppError::Invalid(format!("it can't be validated, {unknown:?}"))),
+    }
+}
+
+// Same as `sign_and_send_batch_calls`, but only validating the batch
+async fn dry_run_batch_calls<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    calls: Vec<Call>,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    batch_mode: BatchMode,
+) -> Result<(), AppError> {
+    let tx_config = params_builder(api, mortality).await?.tip(tip).build();
+
+    match batch_mode {
+        BatchMode::All => dry_run(api, signer, &local::tx().utility().batch_all(calls), tx_config).await,
+        BatchMode::BestEffort => dry_run(api, signer, &local::tx().utility().force_batch(calls), tx_config).await,
+    }
+}
+
+// Here we simulate the call to estimate its fee using
+// `TransactionPaymentApi_query_info`. The fee doesn't depend on who signs, so
+// it's signed by a dev account, which also avoids prompting a Ledger.
+pub async fn estimate_fee<P: Payload>(api: &OnlineClient<CustomConfig>, call: &P) -> Result<u128, AppError> {
+    let signed = api
+        .tx()
+        .create_signed(call, &dev::alice(), Default::default())
+        .await
+        .map_err(check_signed_extensions)?;
+
+    let partial_fee: u128 = signed.partial_fee_estimate().await.map_err(AppError::FeeEstimation)?;
+
+    Ok(partial_fee)
+}
+
+// Fee of the native transfer of `amount` plancks to `dest`, see `estimate_fee`
+pub async fn estimate_fees(
+    api: OnlineClient<CustomConfig>,
+    dest: MultiAddress<AccountId32, ()>,
+    amount: u128,
+) -> Result<u128, AppError> {
+    let balance_transfer_tx = local::tx().balances().transfer_keep_alive(dest, amount);
+
+    estimate_fee(&api, &balance_transfer_tx).await
+}
+
+// Values a native amount in the asset at `asset` at the pool's current price,
+// with the `AssetConversionApi.quote_price_exact_tokens_for_tokens` runtime
+// API. The runtime answers `None` when there's no pool or not enough
+// liquidity to quote the amount.
+pub async fn value_in_asset(
+    api: &OnlineClient<CustomConfig>,
+    native_amount: u128,
+    asset: MultiLocation,
+) -> Result<Option<u128>, AppError> {
+    let include_fee = true;
+
+    let runtime_apis = local::apis().asset_conversion_api().quote_price_exact_tokens_for_tokens(
+        native_location(),
+        asset,
+        native_amount,
+        include_fee
+    );
+
+    Ok(api.runtime_api().at_latest().await?.call(runtime_apis).await?)
+}
+
+// With this fn we convert the estimated fees from the Native asset to our
+// Custom asset.
+pub async fn convert_fees(
+    api: OnlineClient<CustomConfig>,
+    asset_id: u32,
+    amount: u128,
+) -> Result<u128, AppError> {
+    let converted_fee = value_in_asset(&api, amount, local_asset_location(asset_id)).await?;
+
+    let converted_fee = converted_fee.ok_or_else(|| {
+        AppError::QuoteUnavailable(format!("The pool could not quote {amount} native plancks in the asset {asset_id}"))
+    })?;
+
+    Ok(converted_fee)
+}
+
+// Adds `buffer_percent` percent to a fee estimate, for the fee moving between
+// the estimate and the inclusion
+pub fn with_fee_buffer(fee: u128, buffer_percent: u8) -> u128 {
+    fee.saturating_add(fee.saturating_mul(buffer_percent.into()) / 100)
+}
+
+// What transferring `amount` native plancks to `dest` is expected to cost in
+// the asset at `asset`: the native fee estimate valued by the pool, plus
+// `buffer_percent` percent
+pub async fn estimate_fee_in_asset(
+    api: &OnlineClient<CustomConfig>,
+    dest: MultiAddress<AccountId32, ()>,
+    amount: u128,
+    asset: MultiLocation,
+    buffer_percent: u8,
+) -> Result<u128, AppError> {
+    let native_fee = estimate_fees(api.clone(), dest, amount).await?;
+    let fee = value_in_asset(api, native_fee, asset.clone()).await?.ok_or_else(|| {
+        AppError::QuoteUnavailable(format!("The pool could not quote {native_fee} native plancks in {asset:?}"))
+    })?;
+
+    Ok(with_fee_buffer(fee, buffer_percent))
+}
+
+// Checks whether an asset with this id has already been created, by looking it
+// up in the `Assets::Asset` storage
+pub async fn asset_exists(api: &OnlineClient<CustomConfig>, asset_id: u32) -> Result<bool, AppError> {
+    let asset = local::storage().assets().asset(asset_id);
+    let details = api.storage().at_latest().await?.fetch(&asset).await?;
+
+    Ok(details.is_some())
+}
+
+// Checks whether `who` already holds an account of the asset, by looking it up
+// in the `Assets::Account` storage
+pub async fn asset_account_exists(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    who: &AccountId32,
+) -> Result<bool, AppError> {
+    let account = local::storage().assets().account(asset_id, who);
+    let account = api.storage().at_latest().await?.fetch(&account).await?;
+
+    Ok(account.is_some())
+}
+
+pub type PoolInfo = local::runtime_types::pallet_asset_conversion::types::PoolInfo<u32>;
+
+// Storage key of the pool between the two assets in `AssetConversion::Pools`.
+// The map is `Blake2_128Concat` hashed over the SCALE encoded pair, but the
+// generated `pools(asset1, asset2)` address hashes each location on its own,
+// so its key doesn't match the runtime's and is only used for the prefix.
+pub fn pool_key(asset1: MultiLocation, asset2: MultiLocation) -> Vec<u8> {
+    use blake2::{digest::consts::U16, Blake2b, Digest};
+
+    let pair = (asset1, asset2).encode();
+    let mut key = subxt::ext::subxt_core::storage::get_address_root_bytes(
+        &local::storage().asset_conversion().pools_iter(),
+    );
+    key.extend(Blake2b::<U16>::digest(&pair));
+    key.extend(pair);
+    key
+}
+
+// Looks the pool between the two assets up in `AssetConversion::Pools`, by
+// its key as the runtime computes it
+pub async fn pool_info(
+    storage: &Storage<CustomConfig, OnlineClient<CustomConfig>>,
+    asset1: MultiLocation,
+    asset2: MultiLocation,
+) -> Result<Option<PoolInfo>, subxt::Error> {
+    let Some(bytes) = storage.fetch_raw(pool_key(asset1, asset2)).await? else {
+        return Ok(None);
+    };
+
+    Ok(Some(PoolInfo::decode(&mut &bytes[..])?))
+}
+
+// Checks whether the liquidity pool between the two assets has already been
+// created, by looking it up in the `AssetConversion::Pools` storage
+pub async fn pool_exists(
+    api: OnlineClient<CustomConfig>,
+    asset1: MultiLocation,
+    asset2: MultiLocation,
+) -> Result<bool, AppError> {
+    let storage = api.storage().at_latest().await?;
+
+    Ok(pool_info(&storage, asset1, asset2).await?.is_some())
+}
+
+// Makes sure the location the fee is going to be paid with is exactly the
+// asset leg of a pool with the native token, as a location that only looks
+// like it (e.g. another pallet instance or index) makes the conversion fail.
+// When there's no such pool, the error lists the asset legs of the native
+// pools that do exist to spot the mismatch.
+async fn verify_fee_asset_pool(
+    api: &OnlineClient<CustomConfig>,
+    fee_asset: &MultiLocation,
+) -> Result<(), AppError> {
+    if pool_exists(api.clone(), native_location(), fee_asset.clone()).await? {
+        return Ok(());
+    }
+
+    let native = native_location::<MultiLocation>().encode();
+    let storage = api.storage().at_latest().await?;
+    let mut pools = storage.iter(local::storage().asset_conversion().pools_iter()).await?;
+
+    // The map is `Blake2_128Concat` hashed, so the key ends with the SCALE
+    // encoded pair of locations, after the 32 bytes prefix and 16 bytes hash
+    let mut asset_legs = Vec::new();
+    while let Some(pool) = pools.next().await {
+        let pool = pool?;
+        let (asset1, asset2) =
+            <(MultiLocation, MultiLocation)>::decode(&mut &pool.key_bytes[48..]).map_err(subxt::Error::from)?;
+        if asset1.encode() == native {
+            asset_legs.push(asset2);
+        } else if asset2.encode() == native {
+            asset_legs.push(asset1);
+        }
+    }
+
+    Err(AppError::FeeAsset(format!(
+        "No pool pairs the native token with the fee asset {fee_asset:?}, the native pools are with {asset_legs:?}"
+    )))
+}
+
+// `amount * factor / 1000`, an error rather than a wrapped or saturated
+// result when it doesn't fit, e.g. a slippage bound on a huge quote
+fn per_mille(amount: u128, factor: u128, what: &str) -> Result<u128, AppError> {
+    amm::mul_div(amount, factor, 1000).ok_or_else(|| AppError::ArithmeticOverflow(format!("{what} ({amount} * {factor}/1000)")))
+}
+
+// Lowest amount accepted for `desired` with a tolerance of `tolerance_bps`
+// basis points, e.g. 50 accepts down to 99.5% of it
+fn apply_slippage(desired: u128, tolerance_bps: u16) -> Result<u128, AppError> {
+    if tolerance_bps > 10_000 {
+        return Err(AppError::Setup(format!(
+            "a slippage tolerance of {tolerance_bps} bps is above 100%"
+        )));
+    }
+
+    // Split so that `desired * 10_000` can't overflow
+    let tolerated = 10_000 - u128::from(tolerance_bps);
+    Ok(desired / 10_000 * tolerated + desired % 10_000 * tolerated / 10_000)
+}
+
+// Highest amount accepted for `quoted` with a tolerance of `tolerance_bps`
+// basis points, e.g. 50 accepts up to 100.5% of it
+fn apply_slippage_above(quoted: u128, tolerance_bps: u16) -> Result<u128, AppError> {
+    let tolerated = 10_000 + u128::from(tolerance_bps);
+    amm::mul_div(quoted, tolerated, 10_000)
+        .ok_or_else(|| AppError::ArithmeticOverflow(format!("{quoted} with a tolerance of {tolerance_bps} bps")))
+}
+
+// Rejects amounts below `minimum` before anything is built or signed, as a
+// transfer of nothing still pays its fee
+fn check_amount(amount: u128, minimum: u128) -> Result<(), AppError> {
+    if amount < minimum {
+        return Err(AppError::Invalid(format!(
+            "refusing to transfer {amount}, the minimum is {minimum}"
+        )));
+    }
+
+    Ok(())
+}
+
+// Thin pools can't swap the fee to the native token. With `--auto-fallback`
+// the pool between the native token and the fee asset has to hold at least
+// the native fee, otherwise the fee is paid in the native token.
+async fn check_fee_liquidity(
+    api: &OnlineClient<CustomConfig>,
+    asset: &MultiLocation,
+    native_fee: u128,
+) -> Result<(), AppError> {
+    let reserves = local::apis()
+        .asset_conversion_api()
+        .get_reserves(native_location(), asset.clone());
+    let Some((native_reserve, _)) = api.runtime_api().at_latest().await?.call(reserves).await? else {
+        return Err(AppError::FeeAsset(format!("There's no pool with reserves between the native token and {asset:?}")));
+    };
+
+    if native_reserve < native_fee {
+        return Err(AppError::FeeAsset(format!(
+            "The pool holds {native_reserve} native plancks, not enough to swap the fee of {native_fee}"
+        )));
+    }
+
+    Ok(())
+}
+
+// Here we make a Native asset transfer while paying the tx fees with our custom
+// asset, using the `AssetConversionTxPayment` signed extension that we configured
+// as `ChargeAssetTxPayment`. Amounts below `min_amount` are rejected. The tip
+// is in native plancks and gets converted to the asset along with the fee.
+// The fee is estimated in the asset, with `fee_buffer_percent` percent on top,
+// before signing, and the fee actually paid is checked against it.
+// With `auto_fallback` the fee is paid in the native token instead when the
+// pool is too thin for it, see `check_fee_liquidity`. Returns how the fee was
+// paid along with the receipt, e.g. to account for the asset it took.
+#[allow(clippy::too_many_arguments)]
+pub async fn sign_and_send_transfer<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    dest: MultiAddress<AccountId32, ()>,
+    amount: u128,
+    min_amount: u128,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    fee_mode: FeeMode,
+    auto_fallback: bool,
+    fee_buffer_percent: u8,
+    native_decimals: u8,
+) -> Result<(SubmissionReceipt, FeePayment), AppError> {
+    check_amount(amount, min_amount)?;
+
+    let fee_mode = match fee_mode {
+        FeeMode::Asset(multi) if auto_fallback => {
+            let native_fee = estimate_fees(api.clone(), dest.clone(), amount).await?;
+            match check_fee_liquidity(&api, &multi, native_fee).await {
+                Ok(()) => FeeMode::Asset(multi),
+                Err(e) => {
+                    warn!("{e}, paying the fee in the native token");
+                    FeeMode::Native
+                }
+            }
+        }
+        fee_mode => fee_mode,
+    };
+
+    let expected_fee = match &fee_mode {
+        FeeMode::Native => estimate_fees(api.clone(), dest.clone(), amount).await?.saturating_add(tip),
+        FeeMode::Asset(multi) => {
+            verify_fee_asset_pool(&api, multi).await?;
+
+            let expected_fee =
+                estimate_fee_in_asset(&api, dest.clone(), amount, *multi.clone(), fee_buffer_percent).await?;
+            let tip_in_asset = match tip {
+                0 => 0,
+                tip => value_in_asset(&api, tip, *multi.clone()).await?.unwrap_or_default(),
+            };
+            expected_fee.saturating_add(tip_in_asset)
+        }
+    };
+    let fee_decimals = match &fee_mode {
+        FeeMode::Native => native_decimals,
+        FeeMode::Asset(multi) => location_decimals(&api, multi, native_decimals).await,
+    };
+    info!(
+        expected_fee,
+        ?fee_mode,
+        display = format_units(expected_fee, fee_decimals),
+        "Estimated the transfer fee"
+    );
+
+    let transfer_hash = call_hash(&Call::Balances(BalancesCall::transfer_keep_alive {
+        dest: dest.clone(),
+        value: amount,
+    }));
+    let balance_transfer_tx = local::tx().balances().transfer_keep_alive(dest, amount);
+    
+    let builder = params_builder(&api, mortality).await?;
+    let tx_config = match &fee_mode {
+        FeeMode::Native => builder.tip(tip),
+        FeeMode::Asset(multi) => builder.tip_of(tip, *multi.clone()),
+    }
+    .build();
+    
+    // Here we send the Native asset transfer and wait for it to be finalized, then
+    // look at its events to tell whether we succesfully paid the fees with our
+    // custom asset or they fell back to the native token
+    let signed = create_verified(&api, &balance_transfer_tx, signer, tx_config).await?;
+    info!(extrinsic = ?signed.hash(), call_hash = ?transfer_hash, "Submitting the transfer");
+
+    let receipt = submit_idempotent(&api, policy, signed).await?;
+    if let Err(e) = log_tx_events(&receipt.events) {
+        warn!("Could not log the events: {e}");
+    }
+
+    // The `AssetTxFeePaid` event says exactly how much of the asset went to
+    // the fee. Without it the fee fell back to the native token.
+    let payment = fee_payment(&receipt.events)?;
+    match (&payment, &fee_mode) {
+        (FeePayment::Asset { amount, tip, payer, asset_id }, _) => {
+            info!(
+                fee = amount,
+                tip,
+                %payer,
+                asset = ?asset_id,
+                display = format_units(*amount, fee_decimals),
+                "Balance transfer submitted and fee paid succesfully in the asset"
+            );
+            if *amount > expected_fee {
+                warn!(fee = amount, expected_fee, "The fee paid is above the estimate");
+            }
+        }
+        (FeePayment::Native { amount }, FeeMode::Native) => {
+            info!(
+                fee = amount,
+                display = format_units(*amount, native_decimals),
+                "Balance transfer submitted and fee paid in the native token"
+            )
+        }
+        (FeePayment::Native { amount }, FeeMode::Asset(multi)) => {
+            warn!(
+                fee = amount,
+                asset = ?multi,
+                display = format_units(*amount, native_decimals),
+                "Balance transfer submitted but no `AssetTxFeePaid` was emitted: the fee was paid in the native token, not the asset"
+            )
+        }
+    }
+    Ok((receipt, payment))
+}
+
+// Free native balance of the account, 0 when it doesn't exist
+async fn free_balance(
+    api: &OnlineClient<CustomConfig>,
+    account: AccountId32,
+) -> Result<u128, AppError> {
+    let account = local::storage().system().account(account);
+    let info = api.storage().at_latest().await?.fetch(&account).await?;
+
+    Ok(info.map(|info| info.data.free).unwrap_or(0))
+}
+
+// Balance of the asset held by `who`, 0 when it holds none
+pub async fn asset_balance(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    who: &AccountId32,
+) -> Result<u128, AppError> {
+    let account = local::storage().assets().account(asset_id, who.clone());
+    let account = api.storage().at_latest().await?.fetch(&account).await?;
+
+    Ok(account.map(|account| account.balance).unwrap_or(0))
+}
+
+// Mints the asset to every beneficiary in one `batch_all`, so either all of
+// them get their amount or none does, then logs what each of them holds.
+// Batches of more than `max_len` mints are refused rather than risking an
+// extrinsic too large for a block. With `validate_only` the batch is
+// validated instead of submitted.
+#[allow(clippy::too_many_arguments)]
+pub async fn mint_to_many<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    beneficiaries: &[(MultiAddress<AccountId32, ()>, u128)],
+    max_len: usize,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    if beneficiaries.is_empty() {
+        return Err(AppError::Invalid("there's no beneficiary to mint to".into()));
+    }
+    if beneficiaries.len() > max_len {
+        return Err(AppError::Invalid(format!(
+            "{} mints don't fit in a batch of at most {max_len}",
+            beneficiaries.len()
+        )));
+    }
+
+    let calls = mint_batch_calls(asset_id, beneficiaries);
+    if validate_only {
+        return dry_run_batch_calls(&api, signer, calls, tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api.clone(), signer, policy, calls, tip, mortality, BatchMode::All).await?.events;
+    let issued = find_events::<local::assets::events::Issued>(&events)?;
+    info!(asset_id, mints = issued.len(), "Minted to the beneficiaries");
+
+    for (beneficiary, amount) in beneficiaries {
+        let MultiAddress::Id(account) = beneficiary else {
+            continue;
+        };
+        let balance = asset_balance(&api, asset_id, account).await?;
+        info!(%account, minted = amount, balance, "Balance after the mint");
+    }
+
+    Ok(())
+}
+
+// Decimals of the asset from its metadata, 0 when it has none
+pub(crate) async fn asset_decimals(api: &OnlineClient<CustomConfig>, asset_id: u32) -> Result<u8, AppError> {
+    let metadata = local::storage().assets().metadata(asset_id);
+    let metadata = api.storage().at_latest().await?.fetch(&metadata).await?;
+
+    Ok(metadata.map(|metadata| metadata.decimals).unwrap_or(0))
+}
+
+// Decimals amounts of the token at `location` are displayed with: the native
+// token's, or the asset's from its metadata. Anything else, or metadata that
+// can't be read, gets 0, showing the raw amount.
+pub(crate) async fn location_decimals(api: &OnlineClient<CustomConfig>, location: &MultiLocation, native_decimals: u8) -> u8 {
+    if location.encode() == native_location::<MultiLocation>().encode() {
+        return native_decimals;
+    }
+    let Some(asset_id) = local_asset_id(location) else {
+        return 0;
+    };
+
+    asset_decimals(api, asset_id).await.unwrap_or_else(|e| {
+        warn!(asset_id, "Could not read the asset decimals: {e}");
+        0
+    })
+}
+
+// Pallets the example builds calls for, reads the storage of or decodes the
+// events of
+const CHECKED_PALLETS: [&str; 4] = ["Assets", "AssetConversion", "Utility", "Balances"];
+
+// Compares the pallets the example uses between the metadata it was compiled
+// against and the connected node's, as a runtime that changed them fails
+// later with decoding errors that don't say why
+pub async fn check_metadata_compatibility(api: &OnlineClient<CustomConfig>) -> Result<(), AppError> {
+    let compiled = subxt::Metadata::decode(&mut &include_bytes!("../metadata/asset_hub_metadata.scale")[..])
+        .map_err(|e| AppError::MetadataMismatch(format!("the compiled metadata can't be decoded ({e})")))?;
+    let live = api.metadata();
+
+    let divergent: Vec<&str> = CHECKED_PALLETS
+        .into_iter()
+        .filter(|name| {
+            let compiled = compiled.pallet_by_name(name).map(|pallet| pallet.hash());
+            let live = live.pallet_by_name(name).map(|pallet| pallet.hash());
+            compiled.is_none() || compiled != live
+        })
+        .collect();
+
+    if divergent.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::MetadataMismatch(divergent.join(", ")))
+}
+
+// Follows the runtime upgrades and fails as soon as the spec version differs
+// from the one at startup, so a run racing it never straddles an upgrade.
+// Otherwise it never returns, unless the upgrades can no longer be followed.
+pub async fn pin_spec_version(api: OnlineClient<CustomConfig>) -> Result<(), AppError> {
+    let spec_version = api.runtime_version().spec_version;
+    info!(spec_version, "Pinned to the spec version");
+
+    let mut updates = api.updater().runtime_updates().await?;
+    while let Some(update) = updates.next().await {
+        let to = update?.runtime_version().spec_version;
+        if to != spec_version {
+            return Err(AppError::RuntimeUpgraded { from: spec_version, to });
+        }
+    }
+    Err(subxt::Error::Other("the runtime upgrades stopped arriving".into()).into())
+}
+
+// Looks up every asset whose symbol matches by iterating the
+// `Assets::Metadata` storage map. Entries are streamed a page at a time and
+// matches are reported as they're found, so a failure halfway through a big
+// map still shows how far it got.
+async fn find_assets_by_symbol(
+    api: &OnlineClient<CustomConfig>,
+    symbol: &str,
+) -> Result<Vec<u32>, AppError> {
+    let storage = api.storage().at_latest().await?;
+    let mut entries = storage.iter(local::storage().assets().metadata_iter()).await?;
+
+    let mut asset_ids = Vec::new();
+    let mut scanned = 0;
+    while let Some(entry) = entries.next().await {
+        let entry = entry.inspect_err(|_| {
+            warn!(scanned, matched = ?asset_ids, "Iterating the asset metadata failed");
+        })?;
+        scanned += 1;
+        if entry.value.symbol.0 == symbol.as_bytes() {
+            // The map is `Blake2_128Concat` hashed, so the key ends with the
+            // SCALE encoded asset id
+            let mut id_bytes = &entry.key_bytes[entry.key_bytes.len() - 4..];
+            let asset_id = u32::decode(&mut id_bytes).map_err(subxt::Error::from)?;
+            info!(asset_id, symbol, "Found an asset with the symbol");
+            asset_ids.push(asset_id);
+        }
+    }
+
+    Ok(asset_ids)
+}
+
+// Resolves a symbol to the id of the asset using it. Symbols aren't unique on
+// chain, so several matches are reported as an error listing all of them.
+async fn find_asset_by_symbol(
+    api: &OnlineClient<CustomConfig>,
+    symbol: &str,
+) -> Result<Option<u32>, AppError> {
+    let asset_ids = find_assets_by_symbol(api, symbol).await?;
+
+    match asset_ids.as_slice() {
+        [] => Ok(None),
+        [asset_id] => Ok(Some(*asset_id)),
+        _ => Err(AppError::Invalid(format!(
+            "the symbol {symbol} is ambiguous, it's used by the assets {asset_ids:?}"
+        ))),
+    }
+}
+
+// Reads the pool's reserves and the total supply of its LP token. A pool that
+// doesn't exist yet is reported as empty.
+async fn pool_state(
+    api: &OnlineClient<CustomConfig>,
+    asset1: MultiLocation,
+    asset2: MultiLocation,
+) -> Result<(u128, u128, u128), AppError> {
+    let storage = api.storage().at_latest().await?;
+
+    let Some(pool_info) = pool_info(&storage, asset1.clone(), asset2.clone()).await? else {
+        return Ok((0, 0, 0));
+    };
+
+    let lp_asset = local::storage().pool_assets().asset(pool_info.lp_token);
+    let lp_supply = storage.fetch(&lp_asset).await?.map(|details| details.supply).unwrap_or(0);
+
+    let reserves = local::apis().asset_conversion_api().get_reserves(asset1, asset2);
+    let (reserve1, reserve2) = api.runtime_api().at_latest().await?.call(reserves).await?.unwrap_or((0, 0));
+
+    Ok((reserve1, reserve2, lp_supply))
+}
+
+// The calls creating the asset of `spec` with `admin` as its admin, setting
+// its metadata and minting `mint_amount` of it to `admin`. Nobody holds a
+// fresh asset, so `admin` is touched first, see `mint_calls`.
+pub fn create_asset_calls(spec: &AssetSpec, admin: &AccountId32) -> Vec<Call> {
+    let address: MultiAddress<AccountId32, ()> = admin.clone().into();
+    vec![
+        create_asset_call(spec.id, address.clone(), spec.min_balance),
+        set_asset_metadata_call(spec.id, spec.name.as_bytes().to_vec(), spec.symbol.as_bytes().to_vec(), spec.decimals),
+        touch_other_call(spec.id, address.clone()),
+        mint_token_call(spec.id, address, spec.mint_amount),
+    ]
+}
+
+// The calls minting `amount` of the asset to `beneficiary`. Depending on the
+// runtime, minting to an account that never held the asset fails with
+// `NoAccount` unless it's touched first, so that's done when needed.
+pub async fn mint_calls(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    beneficiary: &AccountId32,
+    amount: u128,
+) -> Result<Vec<Call>, AppError> {
+    let address: MultiAddress<AccountId32, ()> = beneficiary.clone().into();
+    let mut calls = Vec::new();
+    if !asset_account_exists(api, asset_id, beneficiary).await? {
+        calls.push(touch_other_call(asset_id, address.clone()));
+    }
+    calls.push(mint_token_call(asset_id, address, amount));
+    Ok(calls)
+}
+
+// Whether a submission failed because an asset or a pool it creates exists
+// already: `AssetHubClient::ensure_asset` or `ensure_pool` checked for it, but
+// someone else created it in between.
+pub(crate) fn reports_already_exists(error: &AppError) -> bool {
+    let AppError::Subxt(subxt::Error::Runtime(subxt::error::DispatchError::Module(module_error))) = error else {
+        return false;
+    };
+    matches!(module_error.details_string().as_str(), "Assets::InUse" | "AssetConversion::PoolExists")
+}
+
+// The tip every transaction of the run pays, priced from recent blocks. When
+// they can't be sampled nothing is tipped.
+async fn run_tip(api: &OnlineClient<CustomConfig>, cli: &Cli, native_decimals: u8) -> u128 {
+    let Some(priority) = cli.priority else {
+        return 0;
+    };
+    match tip_for_priority(api, priority).await {
+        Ok(tip) => {
+            info!(tip, ?priority, display = format_units(tip, native_decimals), "Tipping for the priority");
+            tip
+        }
+        Err(e) => {
+            warn!("Could not sample the recent tips, not tipping: {e}");
+            0
+        }
+    }
+}
+
+// Runs what the command line asks for, signing with `signer`: a subcommand,
+// or the example without one, see `run_example`. Whatever fails is returned
+// to the caller, which picks the exit code.
+pub async fn run<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    cli: &Cli,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    let (native_decimals, _) = native_token;
+
+    // The asset we work with, either the one the example creates or an
+    // existing one picked by its symbol
+    let asset_id = match &cli.asset_symbol {
+        Some(symbol) => {
+            let asset_id = find_asset_by_symbol(&api, symbol)
+                .await?
+                .ok_or_else(|| AppError::Invalid(format!("there's no asset with the symbol {symbol}")))?;
+            info!(asset_id, symbol, "Using the asset");
+            asset_id
+        }
+        None => cli.asset_id,
+    };
+
+    // The status, the listings, the queries, whoami and the fee and price
+    // quotes only read the chain, so they don't change with `--dynamic`
+    let reads_only = matches!(
+        cli.command,
+        Some(
+            Command::Status
+                | Command::List
+                | Command::Balances { .. }
+                | Command::ListPools
+                | Command::Query { .. }
+                | Command::Whoami
+                | Command::FeeBreakdown
+                | Command::PriceCurve { .. }
+                | Command::QuotePath { .. }
+        )
+    );
+    if cli.dynamic && !reads_only {
+        return dynamic::run(&api, signer, policy, cli, asset_id).await;
+    }
+
+    // Transfers, quotes and the watcher go through an `AssetHubClient`, see
+    // `commands`. The other subcommands are tipped like the example's own
+    // transactions.
+    let client = || AssetHubClient::new(api.clone(), BorrowedSigner(signer))
+        .with_fee_buffer(cli.fee_buffer_percent)
+        .with_submit_policy(policy.clone())
+        .with_mortality(cli.mortal_period)
+        .with_min_amount(cli.min_amount);
+    match &cli.command {
+        None => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            run_example(api, signer, policy, cli, asset_id, tip, native_token).await
+        }
+        Some(Command::Setup) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            let (asset_config, params, options) = setup::from_cli(&api, signer, policy, cli, asset_id, tip)?;
+            setup::run(api, signer, &asset_config, &params, &options, cli.dry_run, native_token).await
+        }
+        Some(Command::Status) => {
+            status::run(&api, asset_id, signer.account_id(), native_token, cli.json, cli.plain).await
+        }
+        Some(Command::List) => list::assets(&api, cli.json).await,
+        Some(Command::Balances { account }) => list::balances(&api, asset_id, account.as_ref(), cli.json).await,
+        Some(Command::ListPools) => list::pools(&api, cli.json).await,
+        Some(Command::Whoami) => whoami(&api, signer.account_id(), asset_id, native_token).await,
+        Some(Command::Estimate) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            let (asset_config, params, options) = setup::from_cli(&api, signer, policy, cli, asset_id, tip)?;
+            setup::estimate_only(api, signer, cli, &asset_config, &params, &options, native_token).await
+        }
+        Some(Command::FeeBreakdown) => fee::print_breakdown(&api, native_token).await,
+        Some(Command::PriceCurve { amount }) => {
+            route::print_price_curve(&api, asset_id, *amount, *native_decimals, cli.plain).await
+        }
+        Some(Command::QuotePath { path, amount }) => compare_path_quote(&api, path, *amount, *native_decimals).await,
+        Some(Command::Replay { file }) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            replay(api, signer, policy, file, tip, cli.mortal_period, cli.dry_run).await
+        }
+        Some(Command::MintTo { mints, max_batch_len }) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            let beneficiaries: Vec<_> = mints.iter().map(|(account, amount)| (account.clone().into(), *amount)).collect();
+            mint_to_many(api, signer, policy, asset_id, &beneficiaries, *max_batch_len, tip, cli.mortal_period, cli.dry_run).await
+        }
+        Some(Command::Query { queries }) => {
+            query::run_queries(&api, queries, native_token).await;
+            Ok(())
+        }
+        Some(Command::Swap { amount, path, min_out, exact_out }) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            swap::run(api, signer, policy, cli, asset_id, *amount, path, *min_out, *exact_out, tip, *native_decimals).await
+        }
+        Some(Command::Liquidity { action }) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            liquidity::run(api, signer, policy, asset_id, action, cli.slippage_bps, tip, cli.mortal_period, cli.dry_run).await
+        }
+        Some(Command::Admin { action }) => {
+            let tip = run_tip(&api, cli, *native_decimals).await;
+            admin::run(api, signer, policy, action, tip, cli.mortal_period, cli.dry_run).await
+        }
+        Some(Command::Transfer { dest, amount, fee_in_asset }) => {
+            commands::transfer(&client(), asset_id, dest.clone(), *amount, *fee_in_asset, cli.dry_run).await
+        }
+        Some(Command::Quote { amount, from, to, exact_out }) => {
+            commands::quote(&client(), asset_id, *amount, from.clone(), to.clone(), *exact_out).await
+        }
+        // The watcher runs for long enough to outlive a node restart, so it
+        // gets a client that reconnects
+        // Through the light client there's no url to reconnect to
+        #[cfg(feature = "light-client")]
+        Some(Command::Watch) if cli.chain_spec.is_some() => commands::watch(&client(), *native_decimals).await,
+        Some(Command::Watch) => {
+            let backoff = Duration::from_millis(cli.retry_backoff_ms);
+            let mut client = AssetHubClient::connect(&cli.urls[0], BorrowedSigner(signer), cli.max_retries, backoff)
+                .await?
+                .with_submit_policy(policy.clone());
+            if let Some(genesis_hash) = cli.genesis_hash {
+                client = client.with_genesis_hash(genesis_hash);
+            }
+            commands::watch(&client, *native_decimals).await
+        }
+    }
+}
+
+// The example, run when no subcommand is given. It sets the stage up, see
+// `setup`, and pays the fee of a transfer to Bob in the asset.
+async fn run_example<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    cli: &Cli,
+    asset_id: u32,
+    tip: u128,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    if cli.urls.len() > 1 {
+        let native: MultiLocation = native_location();
+        let asset: MultiLocation = local_asset_location(asset_id);
+
+        if let Err(e) = compare_endpoints(&cli.urls, asset_id, native, asset, signer.account_id(), native_token).await {
+            warn!("Could not compare the endpoints: {e}");
+        }
+    }
+
+    let (asset_config, params, options) = setup::from_cli(&api, signer, policy, cli, asset_id, tip)?;
+
+    if cli.dry_run {
+        setup::dry_run_setup(&api, signer, &asset_config, &params, &options, native_token).await?;
+
+        // The transfer is validated on its own, which only goes through once
+        // the asset and pool exist
+        let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+        let transfer = local::tx().balances().transfer_keep_alive(dest, TRANSFER_AMOUNT);
+        let tx_config = params_builder(&api, cli.mortal_period)
+            .await?
+            .tip_of(tip, local_asset_location(asset_id))
+            .build();
+        match dry_run(&api, signer, &transfer, tx_config).await {
+            Ok(()) => info!("Dry run: the transfer would be accepted"),
+            Err(e) => warn!("Dry run: the transfer would be rejected: {e}"),
+        }
+        return Ok(());
+    }
+
+    // Setup the stage. Once finalized the asset and pool are there for the fee
+    // estimate below.
+    let receipt = setup::prepare_setup(api.clone(), signer, &asset_config, &params, &options, native_token).await?;
+    info!(block = ?receipt.block_hash, extrinsic = ?receipt.extrinsic_hash, "Setup finalized");
+
+    // The fee conversion and the swaps read the pool at the latest finalized
+    // block, which has to see the liquidity first. That's all there is to wait
+    // for: the asset is created by `create`, so it's never sufficient and its
+    // fees are always swapped through the pool.
+    let pool_client = AssetHubClient::new(api.clone(), BorrowedSigner(signer));
+    let pool = pool_client.wait_for_pool_liquidity(native_location(), local_asset_location(asset_id), 1, POOL_WAIT_TIMEOUT);
+    if let Err(e) = pool.await {
+        warn!("{e}, trying the transfer anyway");
+    }
+
+    let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+
+    // Here we estimate the tx fees, in the native token and in our custom asset
+    match estimate_fee_both_units(&api, asset_id, dest.clone(), TRANSFER_AMOUNT, native_token).await {
+        Ok(estimate) => info!(
+            fee_plancks = estimate.native_fee,
+            fee_in_asset = estimate.asset_fee,
+            asset_id,
+            "Estimated the fee\n{estimate}"
+        ),
+        Err(e) => warn!("Could not estimate the fee: {e}"),
+    }
+
+    // Here we create and submit the native asset transfer passing the custom 
+    // asset's MultiLocation to pay the fees
+    let (receipt, payment) = sign_and_send_transfer(
+        api.clone(),
+        signer,
+        policy,
+        dest,
+        TRANSFER_AMOUNT,
+        cli.min_amount,
+        tip,
+        cli.mortal_period,
+        FeeMode::Asset(Box::new(local_asset_location(asset_id))),
+        cli.auto_fallback,
+        cli.fee_buffer_percent,
+        native_token.0,
+    )
+    .await?;
+
+    match payment {
+        FeePayment::Asset { amount, .. } => info!(
+            block = ?receipt.block_hash,
+            extrinsic = ?receipt.extrinsic_hash,
+            asset_fee = amount,
+            "Transfer finalized"
+        ),
+        FeePayment::Native { .. } => info!(block = ?receipt.block_hash, extrinsic = ?receipt.extrinsic_hash, "Transfer finalized"),
+    }
+
+    // With other assets, a swap from ours to the first one goes through their
+    // pool, for 1% of what it was seeded with so the price barely moves
+    if let Some(other) = cli.extra_assets.first() {
+        let path = vec![local_asset_location(asset_id), local_asset_location(other.id)];
+        let amount = params.liquidity_asset / 100;
+        swap::swap_along_path(api, signer, policy, path, amount, None, cli.slippage_bps, cli.min_amount, tip, cli.mortal_period, false, native_token.0, cli.slippage_warn_bps).await?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::Encode;
+
+    use super::*;
+
+    // SCALE encodings of the locations as the Asset Hub Westend runtime
+    // encodes them, e.g. in the keys of `AssetConversion::Pools`
+    const NATIVE_LOCATION: [u8; 2] = [0x01, 0x00];
+    // parents: 0, X2, PalletInstance(50), GeneralIndex(compact 1)
+    const LOCAL_ASSET_LOCATION: [u8; 6] = [0x00, 0x02, 0x04, 0x32, 0x05, 0x04];
+
+    // The metadata the example is compiled against
+    pub(crate) fn metadata() -> subxt::Metadata {
+        subxt::Metadata::decode(&mut &include_bytes!("../metadata/asset_hub_metadata.scale")[..]).unwrap()
+    }
+
+    #[test]
+    fn native_location_encoding() {
+        assert_eq!(native_location::<MultiLocation>().encode(), NATIVE_LOCATION);
+    }
+
+    #[test]
+    fn local_asset_location_encoding() {
+        assert_eq!(local_asset_location::<MultiLocation>(ASSET_ID).encode(), LOCAL_ASSET_LOCATION);
+    }
+
+    #[test]
+    fn slippage_tolerance() {
+        assert_eq!(apply_slippage(10_000, 50).unwrap(), 9_950);
+        assert_eq!(apply_slippage(u128::MAX, 0).unwrap(), u128::MAX);
+        assert_eq!(apply_slippage(123, 10_000).unwrap(), 0);
+        assert!(apply_slippage(123, 10_001).is_err());
+        // Exactly `floor(u128::MAX * 9_950 / 10_000)`, without wrapping
+        assert_eq!(apply_slippage(u128::MAX, 50).unwrap(), amm::mul_div(u128::MAX, 9_950, 10_000).unwrap());
+        assert_eq!(per_mille(u128::MAX, 990, "min").unwrap(), amm::mul_div(u128::MAX, 99, 100).unwrap());
+        assert!(matches!(per_mille(u128::MAX, 1010, "max"), Err(AppError::ArithmeticOverflow(_))));
+        assert_eq!(apply_slippage_above(10_000, 50).unwrap(), 10_050);
+        assert!(matches!(apply_slippage_above(u128::MAX, 1), Err(AppError::ArithmeticOverflow(_))));
+    }
+
+    #[test]
+    fn local_asset_id_roundtrip() {
+        assert_eq!(local_asset_id(&local_asset_location::<MultiLocation>(ASSET_ID)), Some(ASSET_ID));
+        assert_eq!(local_asset_id(&native_location::<MultiLocation>()), None);
+    }
+
+    // Eras only have power of two periods between 4 and 65536 blocks
+    #[test]
+    fn mortal_period_rounding() {
+        assert_eq!(MortalityConfig::new(1).unwrap().period_blocks, 4);
+        assert_eq!(MortalityConfig::new(64).unwrap().period_blocks, 64);
+        assert_eq!(MortalityConfig::new(100).unwrap().period_blocks, 128);
+        assert_eq!(MortalityConfig::new(65_536).unwrap().period_blocks, 65_536);
+        assert!(MortalityConfig::new(0).is_err());
+        assert!(MortalityConfig::new(65_537).is_err());
+    }
+
+    // The roles handed over survive the SCALE round trip the runtime decodes
+    // the call with
+    #[test]
+    fn team_calls_decode_to_their_roles() {
+        let (alice, bob, charlie): (AccountId32, AccountId32, AccountId32) = (
+            dev::alice().public_key().into(),
+            dev::bob().public_key().into(),
+            dev::charlie().public_key().into(),
+        );
+
+        let call = set_team_call(ASSET_ID, alice.clone().into(), bob.clone().into(), charlie.clone().into());
+        let Call::Assets(AssetsCall::set_team { id, issuer, admin, freezer }) =
+            Call::decode(&mut &call.encode()[..]).unwrap()
+        else {
+            panic!("not a set_team call");
+        };
+        assert_eq!(id, ASSET_ID);
+        assert_eq!(issuer, MultiAddress::Id(alice));
+        assert_eq!(admin, MultiAddress::Id(bob.clone()));
+        assert_eq!(freezer, MultiAddress::Id(charlie));
+
+        let call = transfer_ownership_call(ASSET_ID, bob.clone().into());
+        let Call::Assets(AssetsCall::transfer_ownership { id, owner }) = Call::decode(&mut &call.encode()[..]).unwrap()
+        else {
+            panic!("not a transfer_ownership call");
+        };
+        assert_eq!(id, ASSET_ID);
+        assert_eq!(owner, MultiAddress::Id(bob));
+    }
+
+    // `AssetConversion::Pools` is a `Blake2_128Concat` map keyed by the pair
+    // of locations, with the native token first
+    #[test]
+    fn native_pool_storage_key() {
+        use sp_core::hashing::{blake2_128, twox_128};
+
+        let metadata = metadata();
+
+        let pools = local::storage().asset_conversion().pools_iter();
+        let prefix = subxt::ext::subxt_core::storage::get_address_bytes(&pools, &metadata).unwrap();
+        let key = pool_key(native_location(), local_asset_location(ASSET_ID));
+        assert!(key.starts_with(&prefix));
+
+        let pair: (MultiLocation, MultiLocation) = (native_location(), local_asset_location(ASSET_ID));
+        let pair = pair.encode();
+        let mut expected = twox_128(b"AssetConversion").to_vec();
+        expected.extend(twox_128(b"Pools"));
+        expected.extend(blake2_128(&pair));
+        expected.extend(&pair);
+        assert_eq!(key, expected);
+    }
+
+    // Explorers hash the call data of the extrinsic, which is what the
+    // generated payloads encode, and a `CallPayload` the same way
+    #[test]
+    fn call_hash_matches_call_data() {
+        use subxt::tx::Payload;
+
+        let metadata = metadata();
+
+        let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+        let call = Call::Balances(BalancesCall::transfer_keep_alive {
+            dest: dest.clone(),
+            value: TRANSFER_AMOUNT,
+        });
+        let call_data = local::tx()
+            .balances()
+            .transfer_keep_alive(dest, TRANSFER_AMOUNT)
+            .encode_call_data(&metadata)
+            .unwrap();
+
+        assert_eq!(call_hash(&call), <CustomConfig as Config>::Hasher::hash(&call_data));
+        assert_eq!(CallPayload(call).encode_call_data(&metadata).unwrap(), call_data);
+    }
+
+    // Encodings of the setup calls, computed once from the metadata. The
+    // first byte is the pallet index (Assets is 50, AssetConversion 56), the
+    // second the call index, so a reordered field or a call built for the
+    // wrong pallet changes them.
+    #[test]
+    fn call_builders_encoding() {
+        let who = AccountId32([1; 32]);
+        let account = "01".repeat(32);
+
+        let calls = [
+            (
+                create_asset_call(ASSET_ID, who.clone().into(), 1),
+                // id: compact 1, admin: MultiAddress::Id, min_balance: u128
+                format!(concat!("3200", "04", "00{}", "01000000000000000000000000000000"), account),
+            ),
+            (
+                set_asset_metadata_call(ASSET_ID, b"Testy".to_vec(), b"TSTY".to_vec(), 10),
+                // id, name and symbol with their compact lengths, decimals
+                concat!("3211", "04", "145465737479", "1054535459", "0a").to_string(),
+            ),
+            (
+                mint_token_call(ASSET_ID, who.clone().into(), 1000),
+                // id, beneficiary, amount: compact 1000
+                format!(concat!("3206", "04", "00{}", "a10f"), account),
+            ),
+            (
+                transfer_asset_call(ASSET_ID, who.clone().into(), 1000),
+                // id, target, amount: compact 1000
+                format!(concat!("3209", "04", "00{}", "a10f"), account),
+            ),
+            (
+                set_min_balance_call(ASSET_ID, 1),
+                // id, min_balance: u128
+                concat!("321c", "04", "01000000000000000000000000000000").to_string(),
+            ),
+            (clear_metadata_call(ASSET_ID), concat!("3212", "04").to_string()),
+            (
+                create_pool_with_native_call(ASSET_ID),
+                format!("3800{}{}", hex::encode(NATIVE_LOCATION), hex::encode(LOCAL_ASSET_LOCATION)),
+            ),
+            (
+                // Given in either order, the pair is sorted by id
+                create_pool_call(asset_pair(2, ASSET_ID).0, asset_pair(2, ASSET_ID).1),
+                // GeneralIndex(compact 2) for the second asset
+                format!("3800{}{}", hex::encode(LOCAL_ASSET_LOCATION), "000204320508"),
+            ),
+            (
+                provide_liquidity_to_token_native_pool_call(ASSET_ID, 100, 10, 99, 9, who),
+                // Both locations, the four u128 amounts and mint_to
+                format!(
+                    "3801{}{}{}{}{}{}{account}",
+                    hex::encode(NATIVE_LOCATION),
+                    hex::encode(LOCAL_ASSET_LOCATION),
+                    hex::encode(100u128.to_le_bytes()),
+                    hex::encode(10u128.to_le_bytes()),
+                    hex::encode(99u128.to_le_bytes()),
+                    hex::encode(9u128.to_le_bytes()),
+                ),
+            ),
+        ];
+
+        for (call, expected) in calls {
+            assert_eq!(hex::encode(call.encode()), expected, "{call:?}");
+        }
+    }
+
+    // A batch mint is the single mints, one per beneficiary and in order
+    #[test]
+    fn mint_batch() {
+        let beneficiaries = [(AccountId32([1; 32]).into(), 1000), (AccountId32([2; 32]).into(), 1)];
+
+        let calls = mint_batch_calls(ASSET_ID, &beneficiaries);
+        let expected: Vec<_> = beneficiaries
+            .iter()
+            .map(|(who, amount)| mint_token_call(ASSET_ID, who.clone(), *amount))
+            .collect();
+        assert_eq!(calls.encode(), expected.encode());
+        assert!(mint_batch_calls(ASSET_ID, &[]).is_empty());
+    }
+
+    // A fresh asset is created with the spec's metadata and minted to its
+    // admin, touched first as it can't hold the asset yet
+    #[test]
+    fn asset_creation_calls() {
+        let admin = AccountId32([1; 32]);
+        let spec = AssetSpec {
+            id: ASSET_ID,
+            name: NAME.into(),
+            symbol: SYMBOL.into(),
+            decimals: 0,
+            min_balance: 1,
+            mint_amount: 1000,
+        };
+
+        let expected = vec![
+            create_asset_call(ASSET_ID, admin.clone().into(), 1),
+            set_asset_metadata_call(ASSET_ID, NAME.into(), SYMBOL.into(), 0),
+            touch_other_call(ASSET_ID, admin.clone().into()),
+            mint_token_call(ASSET_ID, admin.clone().into(), 1000),
+        ];
+        assert_eq!(create_asset_calls(&spec, &admin).encode(), expected.encode());
+    }
+
+    // The failed calls are found from the events `force_batch` and `batch`
+    // emit, encoded as the runtime stores them
+    #[test]
+    fn batch_failures_from_events() {
+        use local::runtime_types::{
+            frame_system::{EventRecord, Phase},
+            pallet_utility::pallet::Event::{BatchCompleted, BatchCompletedWithErrors, BatchInterrupted, ItemCompleted, ItemFailed},
+            sp_runtime::DispatchError,
+        };
+
+        let metadata = metadata();
+        let failures = |events: Vec<_>| {
+            let records: Vec<_> = events
+                .into_iter()
+                .map(|event| EventRecord { phase: Phase::ApplyExtrinsic(0), event: local::Event::Utility(event), topics: Vec::<H256>::new() })
+                .collect();
+            let events = subxt::events::Events::<CustomConfig>::decode_from(records.encode(), metadata.clone());
+            batch_failures(events.iter()).unwrap()
+        };
+
+        let force_batch = vec![ItemCompleted, ItemFailed { error: DispatchError::BadOrigin }, ItemCompleted, BatchCompletedWithErrors];
+        assert_eq!(failures(force_batch), vec![(1, "BadOrigin".to_string())]);
+        let batch = vec![ItemCompleted, ItemCompleted, BatchInterrupted { index: 2, error: DispatchError::CannotLookup }];
+        assert_eq!(failures(batch), vec![(2, "CannotLookup".to_string())]);
+        assert!(failures(vec![ItemCompleted, ItemCompleted, BatchCompleted]).is_empty());
+        assert!(failures(Vec::new()).is_empty());
+    }
+
+    // A transfer of nothing only burns a fee, so it's refused unless
+    // `--min-amount` is lowered to 0
+    #[test]
+    fn zero_transfer_rejected() {
+        use clap::Parser;
+
+        let bob = AccountId32::from(dev::bob().public_key()).to_string();
+        let cli = Cli::try_parse_from(["asset-conversion-example", "transfer", &bob, "0"]).unwrap();
+        let Some(Command::Transfer { amount, .. }) = cli.command else {
+            panic!("expected the transfer subcommand");
+        };
+        assert!(matches!(check_amount(amount, cli.min_amount), Err(AppError::Invalid(_))));
+        assert!(check_amount(1, cli.min_amount).is_ok());
+        assert!(check_amount(0, 0).is_ok());
+    }
+
+    // Refused submissions fail with the reason, the default policy lets them through
+    #[test]
+    fn submission_guard() {
+        let policy = SubmitPolicy { refused: Some("Westend Asset Hub is a Live chain".into()), ..Default::default() };
+
+        assert!(matches!(policy.check(), Err(AppError::Refused(reason)) if reason.contains("Live")));
+        assert!(SubmitPolicy::default().check().is_ok());
+        assert_eq!(SubmitPolicy::default().max_retries, SUBMIT_RETRIES);
+    }
+
+    // Only failures that resubmitting can get past are retried
+    #[test]
+    fn transient_submit_errors() {
+        use subxt::error::{RpcError, TransactionError};
+
+        let rpc = |message: &str| subxt::Error::Rpc(RpcError::ClientError(Box::new(std::io::Error::other(message.to_string()))));
+
+        assert!(is_transient_submit_error(&rpc("ServerError(1016) Immediately Dropped: the pool is full")));
+        assert!(is_transient_submit_error(&subxt::Error::Rpc(RpcError::SubscriptionDropped)));
+        assert!(is_transient_submit_error(&subxt::Error::Transaction(TransactionError::Dropped("limit".into()))));
+        assert!(!is_transient_submit_error(&rpc("ServerError(1010) Invalid Transaction: bad signature")));
+        assert!(!is_transient_submit_error(&rpc("ServerError(1014) Priority is too low")));
+        assert!(!is_transient_submit_error(&subxt::Error::Other("the call failed".into())));
+    }
+
+    // The pool already holding a resubmitted extrinsic means it's in flight
+    #[test]
+    fn already_imported_in_flight() {
+        use subxt::error::RpcError;
+
+        let rpc = |message: &str| subxt::Error::Rpc(RpcError::ClientError(Box::new(std::io::Error::other(message.to_string()))));
+
+        assert!(is_already_imported(&rpc("ServerError(1013) Transaction Already Imported")));
+        assert!(!is_transient_submit_error(&rpc("ServerError(1013) Transaction Already Imported")));
+        assert!(!is_already_imported(&rpc("ServerError(1010) Invalid Transaction: bad signature")));
+    }
+
+    // A signed extrinsic is found in a block by hashing its bytes there, the
+    // same encoded bytes its hash was captured from before submitting it
+    #[test]
+    fn extrinsic_hash_matches_submitted_hash() {
+        use subxt::{client::{ClientState, RuntimeVersion}, ext::subxt_core};
+
+        let client = ClientState::<CustomConfig> {
+            genesis_hash: H256::zero(),
+            runtime_version: RuntimeVersion {
+                spec_version: 0,
+                transaction_version: 0,
+            },
+            metadata: metadata(),
+        };
+        let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+        let transfer = local::tx().balances().transfer_keep_alive(dest, TRANSFER_AMOUNT);
+        let params = WestmintExtrinsicParamsBuilder::new().nonce(0).build();
+        let signed = subxt_core::tx::create_signed(&transfer, &client, &dev::alice(), params).unwrap();
+
+        assert_eq!(extrinsic_hash(signed.encoded()), signed.hash());
+        assert_ne!(<CustomConfig as Config>::Hasher::hash_of(&signed.encoded().to_vec()), signed.hash());
+    }
+
+    // `ChargeAssetTxPayment` adds a compact tip and the optional asset
+    // location to the signed extra
+    #[test]
+    fn asset_tip_encoding() {
+        use subxt::{
+            client::{ClientState, RuntimeVersion},
+            config::{signed_extensions::ChargeAssetTxPayment, ExtrinsicParamsEncoder},
+        };
+
+        let metadata = metadata();
+        let client = ClientState::<CustomConfig> {
+            genesis_hash: H256::zero(),
+            runtime_version: RuntimeVersion {
+                spec_version: 0,
+                transaction_version: 0,
+            },
+            metadata,
+        };
+        let tip = <ChargeAssetTxPayment<CustomConfig> as ExtrinsicParams<CustomConfig>>::new(
+            &client,
+            AssetTip::tip_of(5, native_location()),
+        )
+        .unwrap();
+
+        let mut extra = Vec::new();
+        tip.encode_extra_to(&mut extra);
+        // compact 5, Some, then the location
+        assert_eq!(extra, [&[0x14, 0x01][..], &NATIVE_LOCATION[..]].concat());
+    }
+
+}
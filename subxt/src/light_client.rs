@@ -0,0 +1,31 @@
+use subxt::{backend::rpc::RpcClient, lightclient::LightClient};
+
+use crate::error::AppError;
+
+// An RPC client answered by an embedded smoldot light client, which syncs and
+// checks the chain of `chain_spec` (the JSON of its chain spec) itself instead
+// of trusting a node. Parachains like Asset Hub are followed through their
+// relay chain, whose spec is needed as well.
+pub fn rpc_client(chain_spec: &str, relay_chain_spec: Option<&str>) -> Result<RpcClient, AppError> {
+    let rpc = match relay_chain_spec {
+        Some(relay_chain_spec) => {
+            let (light_client, _relay_rpc) = LightClient::relay_chain(relay_chain_spec).map_err(subxt::Error::from)?;
+            light_client.parachain(chain_spec).map_err(subxt::Error::from)?
+        }
+        None => LightClient::relay_chain(chain_spec).map_err(subxt::Error::from)?.1,
+    };
+
+    Ok(RpcClient::new(rpc))
+}
+
+// `rpc_client` with the chain specs read from these files, as given to
+// `--chain-spec` and `--relay-chain-spec`
+pub fn rpc_client_from_files(chain_spec: &str, relay_chain_spec: Option<&str>) -> Result<RpcClient, AppError> {
+    let read = |path: &str| {
+        std::fs::read_to_string(path)
+            .map_err(|e| AppError::File { path: path.to_string(), reason: e.to_string() })
+    };
+    let relay_chain_spec = relay_chain_spec.map(read).transpose()?;
+
+    rpc_client(&read(chain_spec)?, relay_chain_spec.as_deref())
+}
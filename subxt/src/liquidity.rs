@@ -0,0 +1,203 @@
+use subxt::{tx::Signer, utils::AccountId32, OnlineClient};
+use tracing::info;
+
+use crate::{
+    amm::{self, preview_add_liquidity, quote, LiquidityPreview},
+    apply_slippage,
+    cli::{BatchMode, LiquidityAction},
+    dry_run_batch_calls,
+    error::AppError,
+    local, local_asset_location, native_location, pool_info, pool_state, provide_liquidity_to_token_native_pool_call,
+    remove_liquidity_call, sign_and_send_batch_calls, CustomConfig, MortalityConfig, MultiLocation, SubmitPolicy,
+};
+
+// The `liquidity` subcommand, on the native/asset pool. With `validate_only`
+// the change is validated instead of submitted, see `dry_run`.
+#[allow(clippy::too_many_arguments)]
+pub async fn run<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    action: &LiquidityAction,
+    slippage_bps: u16,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    match *action {
+        LiquidityAction::Add { native, asset } => {
+            add_liquidity(api, signer, policy, asset_id, (native, asset), slippage_bps, tip, mortality, validate_only).await
+        }
+        LiquidityAction::Remove { lp_amount } => {
+            remove_liquidity(api, signer, policy, asset_id, lp_amount, slippage_bps, tip, mortality, validate_only).await
+        }
+    }
+}
+
+// Adds up to `native` and `asset` to the native/custom asset pool and logs
+// what the `LiquidityAdded` event says it took. The pool takes them at its
+// current price, which is previewed first, and each side is accepted down to
+// `slippage_bps` below the preview.
+#[allow(clippy::too_many_arguments)]
+async fn add_liquidity<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    (native, asset): (u128, u128),
+    slippage_bps: u16,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let preview = preview_liquidity(&api, asset_id, native, asset)
+        .await?
+        .ok_or_else(|| AppError::Invalid("the pool would reject the liquidity at its current price".into()))?;
+
+    let call = provide_liquidity_to_token_native_pool_call(
+        asset_id,
+        native,
+        asset,
+        apply_slippage(preview.amount1, slippage_bps)?,
+        apply_slippage(preview.amount2, slippage_bps)?,
+        signer.account_id(),
+    );
+    if validate_only {
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api, signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let added = events
+        .find_first::<local::asset_conversion::events::LiquidityAdded>()?
+        .ok_or_else(|| AppError::UnexpectedOutcome("no `LiquidityAdded` event was emitted".into()))?;
+
+    info!(
+        native_provided = added.amount1_provided,
+        native_expected = preview.amount1,
+        asset_provided = added.amount2_provided,
+        asset_expected = preview.amount2,
+        lp_tokens_minted = added.lp_token_minted,
+        "Added the liquidity"
+    );
+
+    Ok(())
+}
+
+// Native tokens and asset `who` should get back for burning `lp_token_burn` of
+// its LP tokens of the native/asset pool, after the withdrawal fee, given the
+// pool as it is right now. Fails when `who` doesn't hold that many.
+pub async fn expected_withdrawal(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    lp_token_burn: u128,
+    who: AccountId32,
+) -> Result<(u128, u128), AppError> {
+    let native: MultiLocation = native_location();
+    let asset: MultiLocation = local_asset_location(asset_id);
+
+    let storage = api.storage().at_latest().await?;
+    let pool_info = pool_info(&storage, native.clone(), asset.clone())
+        .await?
+        .ok_or_else(|| AppError::Invalid(format!("there's no pool between the native token and the asset {asset_id}")))?;
+
+    let lp_account = local::storage().pool_assets().account(pool_info.lp_token, who);
+    let lp_balance = storage.fetch(&lp_account).await?.map(|account| account.balance).unwrap_or(0);
+    if lp_token_burn > lp_balance {
+        return Err(AppError::Invalid(format!(
+            "can't burn {lp_token_burn} LP tokens, the account only holds {lp_balance}"
+        )));
+    }
+
+    let (reserve1, reserve2, lp_supply) = pool_state(api, native, asset).await?;
+    let fee = api
+        .constants()
+        .at(&local::constants().asset_conversion().liquidity_withdrawal_fee())?;
+    let withdrawal_fee = amm::mul_div(lp_token_burn, fee.0.into(), 1_000_000)
+        .ok_or_else(|| AppError::ArithmeticOverflow("the withdrawal fee".into()))?;
+    let burned = lp_token_burn.saturating_sub(withdrawal_fee);
+    let share = |reserve| {
+        quote(burned, lp_supply, reserve)
+            .ok_or_else(|| AppError::QuoteUnavailable("Could not compute the share of the reserves".into()))
+    };
+    let (expected1, expected2) = (share(reserve1)?, share(reserve2)?);
+
+    Ok((expected1, expected2))
+}
+
+// Burns `lp_token_burn` of the signer's LP tokens of the native/custom asset
+// pool and prints what the `LiquidityRemoved` event says came back. The
+// signer has to hold that many LP tokens. Each side is expected to return its
+// share of the reserves, less the pool's withdrawal fee, and is accepted down
+// to `slippage_bps` below that.
+#[allow(clippy::too_many_arguments)]
+pub async fn remove_liquidity<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    lp_token_burn: u128,
+    slippage_bps: u16,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let native: MultiLocation = native_location();
+    let asset: MultiLocation = local_asset_location(asset_id);
+    let (expected1, expected2) = expected_withdrawal(&api, asset_id, lp_token_burn, signer.account_id()).await?;
+
+    let call = remove_liquidity_call(
+        native,
+        asset,
+        lp_token_burn,
+        apply_slippage(expected1, slippage_bps)?,
+        apply_slippage(expected2, slippage_bps)?,
+        signer.account_id(),
+    );
+    if validate_only {
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api, signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let removed = events
+        .find_first::<local::asset_conversion::events::LiquidityRemoved>()?
+        .ok_or_else(|| AppError::UnexpectedOutcome("no `LiquidityRemoved` event was emitted".into()))?;
+
+    info!(
+        lp_tokens_burned = removed.lp_token_burned,
+        native_received = removed.amount1,
+        native_expected = expected1,
+        asset_received = removed.amount2,
+        asset_expected = expected2,
+        withdrawal_fee = ?removed.withdrawal_fee,
+        "Removed the liquidity"
+    );
+
+    Ok(())
+}
+
+// Predicts what adding liquidity to the native/custom asset pool will take and
+// mint, given the pool as it is right now
+pub async fn preview_liquidity(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    amount1_desired: u128,
+    amount2_desired: u128,
+) -> Result<Option<LiquidityPreview>, AppError> {
+    let native: MultiLocation = native_location();
+    let asset: MultiLocation = local_asset_location(asset_id);
+
+    let (reserve1, reserve2, lp_supply) = pool_state(api, native, asset).await?;
+    let mint_min_liquidity = api
+        .constants()
+        .at(&local::constants().asset_conversion().mint_min_liquidity())?;
+
+    Ok(preview_add_liquidity(
+        reserve1,
+        reserve2,
+        lp_supply,
+        amount1_desired,
+        amount2_desired,
+        mint_min_liquidity,
+    ))
+}
@@ -0,0 +1,187 @@
+use codec::Decode;
+use subxt::{utils::AccountId32, OnlineClient};
+use tracing::{info, warn};
+
+use crate::{error::AppError, local, CustomConfig, MultiLocation};
+
+// The listing subcommands. Each one iterates a storage map `--page-size`
+// entries per request, see `main`, and prints every entry on its own line as
+// soon as it's decoded, tab separated or as a JSON object with `--json`, so
+// a big map is never held in memory and shows up as it's fetched. An entry
+// that fails to arrive stops the listing with an error, after a warning
+// saying how many were printed.
+
+// The `Blake2_128Concat` hashed keys end with their SCALE encoded key, after
+// the 32 bytes of the pallet and storage prefix and the 16 bytes hash
+const HASHED_KEY_START: usize = 48;
+
+fn print_entry(json: bool, value: serde_json::Value, columns: &[String]) {
+    if json {
+        println!("{value}");
+    } else {
+        println!("{}", columns.join("\t"));
+    }
+}
+
+// The `list` subcommand: every asset with metadata, its id, symbol, decimals
+// and name
+pub async fn assets(api: &OnlineClient<CustomConfig>, json: bool) -> Result<(), AppError> {
+    let storage = api.storage().at_latest().await?;
+    let mut entries = storage.iter(local::storage().assets().metadata_iter()).await?;
+
+    let mut printed = 0;
+    while let Some(entry) = entries.next().await {
+        let entry = entry.inspect_err(|_| {
+            warn!(printed, "Iterating the asset metadata failed");
+        })?;
+        let asset_id = u32::decode(&mut &entry.key_bytes[HASHED_KEY_START..]).map_err(subxt::Error::from)?;
+        let symbol = String::from_utf8_lossy(&entry.value.symbol.0);
+        let name = String::from_utf8_lossy(&entry.value.name.0);
+        let decimals = entry.value.decimals;
+
+        print_entry(
+            json,
+            serde_json::json!({ "asset_id": asset_id, "symbol": symbol, "decimals": decimals, "name": name }),
+            &[asset_id.to_string(), symbol.to_string(), decimals.to_string(), name.to_string()],
+        );
+        printed += 1;
+    }
+
+    info!(assets = printed, "Listed the assets");
+    Ok(())
+}
+
+// The `balances` subcommand: the accounts holding the asset and their balance
+// and status, or with `account` the assets that account holds. The map is
+// keyed by asset first, so the latter goes through all of it.
+pub async fn balances(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    account: Option<&AccountId32>,
+    json: bool,
+) -> Result<(), AppError> {
+    let storage = api.storage().at_latest().await?;
+
+    let (mut scanned, mut printed) = (0, 0);
+    // Both addresses point into the same map, but they're of different types
+    // and so are their streams
+    match account {
+        None => {
+            let mut entries = storage.iter(local::storage().assets().account_iter1(asset_id)).await?;
+            while let Some(entry) = entries.next().await {
+                let entry = entry.inspect_err(|_| {
+                    warn!(printed, "Iterating the asset accounts failed");
+                })?;
+                scanned += 1;
+                let status = format!("{:?}", entry.value.status);
+                if print_balance(&entry.key_bytes, entry.value.balance, status, None, json)? {
+                    printed += 1;
+                }
+            }
+        }
+        Some(account) => {
+            let mut entries = storage.iter(local::storage().assets().account_iter()).await?;
+            while let Some(entry) = entries.next().await {
+                let entry = entry.inspect_err(|_| {
+                    warn!(scanned, printed, "Iterating the asset accounts failed");
+                })?;
+                scanned += 1;
+                let status = format!("{:?}", entry.value.status);
+                if print_balance(&entry.key_bytes, entry.value.balance, status, Some(account), json)? {
+                    printed += 1;
+                }
+            }
+        }
+    }
+
+    info!(scanned, accounts = printed, "Listed the balances");
+    Ok(())
+}
+
+// Prints an entry of `Assets::Account` unless it's another account's than
+// `account`. Returns whether it was printed.
+fn print_balance(
+    key_bytes: &[u8],
+    balance: u128,
+    status: String,
+    account: Option<&AccountId32>,
+    json: bool,
+) -> Result<bool, AppError> {
+    let (asset_id, holder) = account_key(key_bytes)?;
+    if account.is_some_and(|account| *account != holder) {
+        return Ok(false);
+    }
+
+    print_entry(
+        json,
+        serde_json::json!({
+            "asset_id": asset_id,
+            "account": holder.to_string(),
+            "balance": balance.to_string(),
+            "status": status,
+        }),
+        &[asset_id.to_string(), holder.to_string(), balance.to_string(), status],
+    );
+    Ok(true)
+}
+
+// The asset id and the account of an `Assets::Account` key: past the
+// prefix, the asset id's hash and the asset id, then the account's hash and
+// the 32 bytes of the account
+pub fn account_key(key_bytes: &[u8]) -> Result<(u32, AccountId32), AppError> {
+    let key = &key_bytes[HASHED_KEY_START..];
+    let asset_id = u32::decode(&mut &key[..4]).map_err(subxt::Error::from)?;
+    let account = AccountId32::decode(&mut &key[key.len() - 32..]).map_err(subxt::Error::from)?;
+    Ok((asset_id, account))
+}
+
+// The `list-pools` subcommand: every pool, its pair of assets and its LP
+// token
+pub async fn pools(api: &OnlineClient<CustomConfig>, json: bool) -> Result<(), AppError> {
+    let storage = api.storage().at_latest().await?;
+    let mut entries = storage.iter(local::storage().asset_conversion().pools_iter()).await?;
+
+    let mut printed = 0;
+    while let Some(entry) = entries.next().await {
+        let entry = entry.inspect_err(|_| {
+            warn!(printed, "Iterating the pools failed");
+        })?;
+        let (asset1, asset2) = <(MultiLocation, MultiLocation)>::decode(&mut &entry.key_bytes[HASHED_KEY_START..])
+            .map_err(subxt::Error::from)?;
+        let lp_token = entry.value.lp_token;
+
+        print_entry(
+            json,
+            serde_json::json!({
+                "asset1": format!("{asset1:?}"),
+                "asset2": format!("{asset2:?}"),
+                "lp_token": lp_token,
+            }),
+            &[format!("{asset1:?}"), format!("{asset2:?}"), lp_token.to_string()],
+        );
+        printed += 1;
+    }
+
+    info!(pools = printed, "Listed the pools");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use subxt_signer::sr25519::dev;
+
+    use super::*;
+    use crate::{tests::metadata, ASSET_ID};
+
+    // The `balances` listing reads the asset id and the account back from
+    // the keys of `Assets::Account`
+    #[test]
+    fn asset_account_storage_key() {
+        let metadata = metadata();
+
+        let bob: AccountId32 = dev::bob().public_key().into();
+        let address = local::storage().assets().account(ASSET_ID, bob.clone());
+        let key = subxt::ext::subxt_core::storage::get_address_bytes(&address, &metadata).unwrap();
+        assert_eq!(account_key(&key).unwrap(), (ASSET_ID, bob));
+    }
+}
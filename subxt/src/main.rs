@@ -1,326 +1,158 @@
+use std::{sync::Arc, time::Duration};
+
 use subxt::{
+    backend::{legacy::{LegacyBackend, LegacyRpcMethods}, rpc::RpcClient},
     OnlineClient,
-    config::{
-        DefaultExtrinsicParamsBuilder,
-        DefaultExtrinsicParams,
-        Config,
-        PolkadotConfig, 
-        SubstrateConfig, 
-        }, 
-        utils::{
-            AccountId32, MultiAddress
-        }
-    };
-use subxt_signer::sr25519::dev::{self};
-
-// Metadata that we'll use for our example
-#[subxt::subxt(runtime_metadata_path = "./metadata/asset_hub_metadata.scale",
-derive_for_type(
-    path = "staging_xcm::v3::multilocation::MultiLocation",
-    derive = "Clone",
-    recursive
-))]
-pub mod local {}
-
-// Types that we retrieve from the Metadata for our example
-type MultiLocation = local::runtime_types::staging_xcm::v3::multilocation::MultiLocation;
-
-use local::runtime_types::xcm::v3::junction::Junction::{GeneralIndex, PalletInstance};
-use local::runtime_types::xcm::v3::junctions::Junctions::{Here, X2};
-
-type Call = local::runtime_types::asset_hub_westend_runtime::RuntimeCall;
-type AssetConversionCall = local::asset_conversion::Call;
-type AssetsCall = local::assets::Call;
-
-// Asset details
-const ASSET_ID: u32 = 1;
-const NAME: &str = "Testy";
-const SYMBOL: &str = "TSTY";
-const URI: &str = "ws://127.0.0.1:9944";
-
-// This is our custom configuration for the signed extensions.
-// We don't need to construct this at runtime,
-// so an empty enum is appropriate:
-pub enum CustomConfig {}
+};
+use subxt_signer::sr25519::dev;
+use tracing::{info, warn, Level};
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt};
+
+#[cfg(feature = "light-client")]
+use asset_conversion_example::light_client;
+use asset_conversion_example::{
+    check_metadata_compatibility,
+    cli::{self, Command},
+    error::AppError,
+    ledger::LedgerSigner,
+    pin_spec_version,
+    reconnect::{check_connection, guard_live_chain, is_transport_error, validate_url, with_backoff},
+    run,
+    signer::AnySigner,
+    units::native_token_info,
+    CustomConfig, SubmitPolicy,
+};
 
-impl Config for CustomConfig {
-    type Hash = <SubstrateConfig as Config>::Hash;
-    type AccountId = <SubstrateConfig as Config>::AccountId;
-    type Address = <PolkadotConfig as Config>::Address;
-    type Signature = <SubstrateConfig as Config>::Signature;
-    type Hasher = <SubstrateConfig as Config>::Hasher;
-    type Header = <SubstrateConfig as Config>::Header;
-    type ExtrinsicParams = DefaultExtrinsicParams<CustomConfig>;
-    type AssetId = MultiLocation;
-
-}
-
-// `pallet-assets` create_asset call
-fn create_asset_call(
-    admin: MultiAddress<AccountId32, ()>,
-    min_balance: u128,
-) -> Result<Call, Box<dyn std::error::Error>> {
-    let call = Call::Assets(AssetsCall::create {
-        id: ASSET_ID,
-        admin: admin,
-        min_balance: min_balance,
-    });
-
-    Ok(call)
-}
-
-// `pallet-assets` set_metadata call
-fn set_asset_metadata_call(
-    asset_id: u32,
-    name: Vec<u8>,
-    symbol: Vec<u8>,
-    decimals: u8,
-) -> Result<Call, Box<dyn std::error::Error>> {
-    let call = Call::Assets(AssetsCall::set_metadata {
-        id: asset_id,
-        name: name,
-        symbol: symbol,
-        decimals: decimals,
-    });
-
-    Ok(call)
-}
-
-// `pallet-assets` create_mint call
-fn mint_token_call(
-    beneficiary: MultiAddress<AccountId32, ()>,
-    amount: u128,
-) -> Result<Call, Box<dyn std::error::Error>> {
-    let call = Call::Assets(AssetsCall::mint {
-        id: ASSET_ID,
-        beneficiary: beneficiary,
-        amount: amount,
-    });
-
-    Ok(call)
-}
-
-// We will use this to create the liquidity pool with a Native asset and our Custom asset
-fn create_pool_with_native_call() -> Result<Call, Box<dyn std::error::Error>> {
-    // Native Asset MultiLocation
-    let asset1: Box<MultiLocation> = Box::new(MultiLocation {
-        parents: 1,
-        interior: Here,
-    });
-    // Our Custom Asset MultiLocation
-    // PalletInstance(50) refers to the pallet-assets in Asset Hub Westend 
-    let asset2: Box<MultiLocation> = Box::new(MultiLocation {
-        parents: 0,
-        interior: X2(PalletInstance(50), GeneralIndex(ASSET_ID.into())),
-    });
-    let call = Call::AssetConversion(AssetConversionCall::create_pool {
-        asset1,
-        asset2,
-    });
-
-    Ok(call)
-}
-
-// We will use this to add liquidity to our liquidity pool
-fn provide_liquidity_to_token_native_pool_call(
-    amount1_desired: u128,
-    amount2_desired: u128,
-    amount1_min: u128,
-    amount2_min: u128,
-    mint_to: AccountId32,
-) -> Result<Call, Box<dyn std::error::Error>> {
-    // Native Asset MultiLocation
-    let asset1: Box<MultiLocation> = Box::new(MultiLocation {
-        parents: 1,
-        interior: Here,
-    });
-    // Our Custom Asset MultiLocation
-    // PalletInstance(50) refers to the pallet-assets in Asset Hub Westend 
-    let asset2: Box<MultiLocation> = Box::new(MultiLocation {
-        parents: 0,
-        interior: X2(PalletInstance(50), GeneralIndex(ASSET_ID.into())),
-    });
-    let call = Call::AssetConversion(AssetConversionCall::add_liquidity {
-        asset1,
-        asset2,
-        amount1_desired: amount1_desired,
-        amount2_desired: amount2_desired,
-        amount1_min: amount1_min,
-        amount2_min: amount2_min,
-        mint_to: mint_to.into(),
-    });
-
-    Ok(call)
-}
-
-// We use this to sign and send the calls that we defined earlier as a single 
-// batch and wait until it's successful
-async fn sign_and_send_batch_calls(
-    api: OnlineClient<CustomConfig>,
-    calls: Vec<Call>,
-) -> Result<(), subxt::Error> {
-    let alice_pair_signer = dev::alice();
-
-    let tx = local::tx().utility().batch_all(calls);
-
-    api.tx()
-        .sign_and_submit_then_watch(&tx, &alice_pair_signer, Default::default())
-        .await?
-        .wait_for_finalized_success()
-        .await?;
-
-    Ok(())
-}
-
-// Here we simulate the native asset transfer to estimate the fees using
-// `TransactionPaymentApi_query_info`
-async fn estimate_fees(
-    api: OnlineClient<CustomConfig>,
-    dest: MultiAddress<AccountId32, ()>,
-    amount: u128,
-    ) -> Result<u128, Box<dyn std::error::Error>> {
-    let alice = dev::alice();
+#[tokio::main]
+async fn main() -> Result<(), AppError> {
+    // Clap reports its own errors, `--help` and `--version` included, with
+    // the exit code each one calls for
+    let settings = match cli::resolve_settings() {
+        Ok(settings) => settings,
+        Err(AppError::Usage(e)) => e.exit(),
+        Err(e) => return Err(e),
+    };
+    init_logging(settings.cli.log_json);
+    for option in &settings.effective {
+        info!(option = %option.long, value = %option.value, source = %option.source, "Effective option");
+    }
+    let cli = settings.cli;
 
-    let balance_transfer_tx = local::tx().balances().transfer_keep_alive(dest, amount);
-    
-    let signed = api.tx().create_signed(&balance_transfer_tx, &alice, Default::default()).await.unwrap();
-    
-    let partial_fee: u128 = signed.partial_fee_estimate().await.unwrap();
-    
-    println!("\nThe estimated fee is: {partial_fee} Plancks\n");
+    if cli.print_schema {
+        println!("{}", serde_json::to_string_pretty(&cli::schema()).unwrap());
+        return Ok(());
+    }
 
-    Ok(partial_fee)
-}
+    // Establish the uri of the local asset hub westend node to which we are 
+    // connecting to and instantiate the api. When several endpoints are given,
+    // the first one is the one we submit to.
+    for url in &cli.urls {
+        validate_url(url)?;
+    }
 
-// With this fn we use the AssetConversionApi.quote_price_exact_tokens_for_tokens
-// to convert the estimated fees from the Native asset to our Custom asset.
-async fn convert_fees(
-    api: OnlineClient<CustomConfig>,
-    amount: u128,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let native: MultiLocation = MultiLocation {
-        parents: 1,
-        interior: Here,
+    // A node that's still starting up is given `--max-retries` more tries
+    let connection_error = |source| AppError::Connection { url: cli.urls[0].clone(), source };
+    let backoff = Duration::from_millis(cli.retry_backoff_ms);
+    #[cfg(feature = "light-client")]
+    let light_client = match &cli.chain_spec {
+        Some(chain_spec) => {
+            info!(chain_spec, "Connecting through the light client");
+            Some(light_client::rpc_client_from_files(chain_spec, cli.relay_chain_spec.as_deref())?)
+        }
+        None => None,
     };
-    let asset: MultiLocation = MultiLocation {
-        parents: 0,
-        interior: X2(PalletInstance(50), GeneralIndex(ASSET_ID.into())),   
+    #[cfg(not(feature = "light-client"))]
+    let light_client = None;
+    let rpc_client = match light_client {
+        Some(rpc_client) => rpc_client,
+        None => with_backoff(cli.max_retries, backoff, is_transport_error, || RpcClient::from_url(&cli.urls[0]))
+            .await
+            .map_err(connection_error)?,
     };
-    let amount = amount;
-    let include_fee = true;
-
-    let runtime_apis = local::apis().asset_conversion_api().quote_price_exact_tokens_for_tokens(
-        native,
-        asset,
-        amount,
-        include_fee
-    );
-
-    let converted_fee = api.runtime_api().at_latest().await.unwrap().call(runtime_apis).await.unwrap();
-
-    println!("\nThe estimated fee in the custom asset is: {:#} TSTY\n", converted_fee.unwrap());
-
-    Ok(())
-}
-
-// Here we make a Native asset transfer while paying the tx fees with our custom
-// asset, using the `AssetConversionTxPayment` signed extension that we configured
-// as `ChargeAssetTxPayment`
-async fn sign_and_send_transfer(
-    api: OnlineClient<CustomConfig>,
-    dest: MultiAddress<AccountId32, ()>,
-    amount: u128,
-    multi: MultiLocation,
-) -> Result<(), subxt::Error> {
-    let alice_pair_signer = dev::alice();
-    let balance_transfer_tx = local::tx().balances().transfer_keep_alive(dest, amount);
-    
-    let tx_config = DefaultExtrinsicParamsBuilder::<CustomConfig>::new()
-    .tip_of(0, multi)
-    .build();
-    
-    // Here we send the Native asset transfer and wait for it to be finalized, while
-    // listening for the `AssetTxFeePaid` event that confirms we succesfully paid
-    // the fees with our custom asset
-    api
-    .tx()
-    .sign_and_submit_then_watch(&balance_transfer_tx, &alice_pair_signer, tx_config)
-    .await?
-    .wait_for_finalized_success()
-    .await?
-    .has::<local::asset_tx_payment::events::AssetTxFeePaid>()?;
-    
-    println!("Balance transfer submitted and fee paid succesfully");
-    Ok(())
-}
-
-// We use this to setup the stage for our transfer, using the calls defined earlier
-// to create our custom asset, set it's metadata, mint it, create the liquidity pool
-// and provide liquidity to it. We send the calls as a batch for simplicity.
-async fn prepare_setup(api: OnlineClient<CustomConfig>) {
-    let alice: MultiAddress<AccountId32, ()> = dev::alice().public_key().into();
-    let address: AccountId32 = dev::alice().public_key().into();
-
-    let mut call_buffer: Vec<Call> = Vec::<Call>::new();
-    call_buffer.push(create_asset_call(alice.clone(), 1).unwrap());
+    let policy = SubmitPolicy {
+        max_retries: cli.max_submit_retries,
+        refused: guard_live_chain(&rpc_client, cli.yes_i_know).await,
+    };
+    let rpc = LegacyRpcMethods::<CustomConfig>::new(rpc_client.clone());
+    // Storage maps are iterated `--page-size` entries at a time, so large maps
+    // are streamed instead of being fetched in one go
+    let backend = LegacyBackend::builder()
+        .storage_page_size(cli.page_size)
+        .build(rpc_client);
+    let api = OnlineClient::<CustomConfig>::from_backend(Arc::new(backend))
+        .await
+        .map_err(connection_error)?;
+    check_connection(&api).await?;
+    // `--dynamic` looks up each call it builds in the node's metadata instead
+    if !cli.dynamic {
+        check_metadata_compatibility(&api).await?;
+    }
 
-    call_buffer.push(
-        set_asset_metadata_call(
-            ASSET_ID,
-            NAME.as_bytes().to_vec(),
-            SYMBOL.as_bytes().to_vec(),
-            0,
-        )
-        .unwrap(),
-    );
+    // With `--pin-spec-version` the run is abandoned as soon as the runtime
+    // is upgraded, so it never straddles an upgrade
+    let pinned = {
+        let (pin, api) = (cli.pin_spec_version, api.clone());
+        async move {
+            if pin {
+                pin_spec_version(api).await
+            } else {
+                std::future::pending().await
+            }
+        }
+    };
 
-    const AMOUNT_TO_MINT: u128 = 100000000000000;
+    let example = async {
+        // Native amounts are displayed with the chain's own decimals and symbol
+        let native_token = native_token_info(&rpc).await;
 
-    call_buffer.push(mint_token_call( alice.clone(), AMOUNT_TO_MINT).unwrap());
- 
-    call_buffer.push(create_pool_with_native_call().unwrap());
+        // The genesis hash ends up in the `CheckGenesis` and `CheckMortality`
+        // signed extensions, so it has to be the one the runtime checks against,
+        // which on forked chains isn't necessarily what the node reports
+        if let Some(genesis_hash) = cli.genesis_hash {
+            info!(?genesis_hash, "Signing against the genesis hash");
+            api.set_genesis_hash(genesis_hash);
+        }
 
-    call_buffer.push(
-        provide_liquidity_to_token_native_pool_call(
-            10000000000,
-            10000000,
-            0,
-            0,
-            address,
-        )
-        .unwrap(),
-    );
+        // Sign with the Ledger device or the secret URI when given, otherwise
+        // with `//Alice`, which only has funds on dev chains
+        let suri = cli.suri.clone().or_else(|| std::env::var("SURI").ok());
+        match (&cli.ledger_command, suri) {
+            // Queries only read, so they're answered without the keys
+            _ if matches!(cli.command, Some(Command::Query { .. })) => run(api, &dev::alice(), &policy, &cli, &native_token).await?,
+            (Some(command), _) => {
+                let ledger = LedgerSigner::new(command)?;
+                run(api, &ledger, &policy, &cli, &native_token).await?
+            }
+            (None, Some(suri)) => run(api, &AnySigner::from_uri(&suri, cli.key_type)?, &policy, &cli, &native_token).await?,
+            (None, None) => {
+                warn!("No --suri given, signing with the `//Alice` dev account");
+                run(api, &dev::alice(), &policy, &cli, &native_token).await?
+            }
+        }
+        Ok(())
+    };
 
-    if let Err(subxt::Error::Runtime(dispatch_err)) =
-        sign_and_send_batch_calls(api, call_buffer).await
-    {
-        eprintln!("Could not dispatch the call: {}", dispatch_err);
+    tokio::select! {
+        result = pinned => result,
+        result = example => result,
     }
 }
 
-#[tokio::main]
-async fn main() {
-    // Establish the uri of the local asset hub westend node to which we are 
-    // connecting to and instantiate the api
-    let api = OnlineClient::<CustomConfig>::from_url(URI).await.unwrap();
-
-    // Setup the stage
-    let _setup = prepare_setup(api.clone()).await;
-
-    // Give it a little time for the tx to be included in the blocks
-    std::thread::sleep(std::time::Duration::from_secs(24));
-
-    let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
-
-    // Here we estimate the tx fees
-    let fee = estimate_fees(api.clone(), dest.clone(), 100000).await.unwrap().try_into();
-
-    let _converted_fee = convert_fees(api.clone(), fee.unwrap()).await;
-
-    // Here we create and submit the native asset transfer passing the custom 
-    // asset's MultiLocation to pay the fees
-    let _result = sign_and_send_transfer(api.clone(), dest, 100000, MultiLocation {
-        parents: 0,
-        interior: X2(PalletInstance(50), GeneralIndex(ASSET_ID.into())),
-    }).await;
+// Diagnostics go through `tracing` to stderr, at `info` unless `RUST_LOG` says
+// otherwise, so what the example prints as its result stays alone on stdout
+fn init_logging(json: bool) {
+    let filter = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|directives| directives.parse::<Targets>().ok())
+        .unwrap_or_else(|| Targets::new().with_default(Level::INFO));
+
+    let builder = tracing_subscriber::fmt().with_writer(std::io::stderr).with_target(false);
+    let result = if json {
+        tracing::subscriber::set_global_default(builder.json().finish().with(filter))
+    } else {
+        tracing::subscriber::set_global_default(builder.finish().with(filter))
+    };
+    if let Err(e) = result {
+        eprintln!("Could not set up the logging: {e}");
+    }
 }
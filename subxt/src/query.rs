@@ -0,0 +1,100 @@
+use std::{fmt, str::FromStr};
+
+use subxt::{utils::AccountId32, OnlineClient};
+use tracing::warn;
+
+use crate::{
+    asset_balance, asset_decimals, convert_fees, error::AppError, free_balance, local_asset_location, native_location, pool_state,
+    units::format_units,
+    CustomConfig,
+};
+
+// A read-only question about the asset conversion state, written as
+// `<kind>:<arguments>`:
+//
+//   reserves:<asset id>                 reserves and LP supply of the native/asset pool
+//   balance:<SS58>                      free native balance
+//   asset-balance:<asset id>:<SS58>     balance of the asset
+//   quote:<amount>:<asset id>           native plancks swapped for the asset
+#[derive(Clone, Debug)]
+pub enum Query {
+    Reserves(u32),
+    Balance(AccountId32),
+    AssetBalance(u32, AccountId32),
+    Quote(u128, u32),
+}
+
+impl FromStr for Query {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = value.split(':').collect();
+        let asset_id = |id: &str| id.parse::<u32>().map_err(|_| format!("invalid asset id {id}"));
+        let account = |address: &str| {
+            AccountId32::from_str(address).map_err(|e| format!("invalid SS58 address {address}: {e}"))
+        };
+
+        match parts.as_slice() {
+            ["reserves", id] => Ok(Query::Reserves(asset_id(id)?)),
+            ["balance", address] => Ok(Query::Balance(account(address)?)),
+            ["asset-balance", id, address] => Ok(Query::AssetBalance(asset_id(id)?, account(address)?)),
+            ["quote", amount, id] => Ok(Query::Quote(
+                amount.parse().map_err(|_| format!("invalid amount {amount}"))?,
+                asset_id(id)?,
+            )),
+            _ => Err(format!(
+                "unknown query {value}, expected reserves:<asset>, balance:<SS58>, asset-balance:<asset>:<SS58> or quote:<amount>:<asset>"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Reserves(asset_id) => write!(f, "reserves:{asset_id}"),
+            Query::Balance(account) => write!(f, "balance:{account}"),
+            Query::AssetBalance(asset_id, account) => write!(f, "asset-balance:{asset_id}:{account}"),
+            Query::Quote(amount, asset_id) => write!(f, "quote:{amount}:{asset_id}"),
+        }
+    }
+}
+
+// The answer to `query`, with native amounts displayed with the chain's
+// decimals and symbol and asset amounts with the decimals of the asset
+async fn answer(api: &OnlineClient<CustomConfig>, query: &Query, native_token: &(u8, String)) -> Result<String, AppError> {
+    let (native_decimals, native_symbol) = native_token;
+    let answer = match query {
+        Query::Reserves(asset_id) => {
+            let (native, asset, lp_supply) =
+                pool_state(api, native_location(), local_asset_location(*asset_id)).await?;
+            format!(
+                "native {} {native_symbol}, asset {}, LP supply {lp_supply}",
+                format_units(native, *native_decimals),
+                format_units(asset, asset_decimals(api, *asset_id).await?)
+            )
+        }
+        Query::Balance(account) => {
+            format!("{} {native_symbol}", format_units(free_balance(api, account.clone()).await?, *native_decimals))
+        }
+        Query::AssetBalance(asset_id, account) => {
+            format_units(asset_balance(api, *asset_id, account).await?, asset_decimals(api, *asset_id).await?)
+        }
+        Query::Quote(amount, asset_id) => {
+            format_units(convert_fees(api.clone(), *asset_id, *amount).await?, asset_decimals(api, *asset_id).await?)
+        }
+    };
+
+    Ok(answer)
+}
+
+// Answers every query in order, without signing anything. A query that fails
+// is reported in place and doesn't stop the others.
+pub async fn run_queries(api: &OnlineClient<CustomConfig>, queries: &[Query], native_token: &(u8, String)) {
+    for query in queries {
+        match answer(api, query, native_token).await {
+            Ok(answer) => println!("{query}: {answer}"),
+            Err(e) => warn!(%query, "The query failed: {e}"),
+        }
+    }
+}
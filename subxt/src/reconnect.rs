@@ -0,0 +1,233 @@
+use std::{future::Future, sync::RwLock, time::Duration};
+
+use subxt::{
+    backend::rpc::{rpc_params, RpcClient},
+    error::RpcError,
+    utils::H256,
+    OnlineClient,
+};
+use tracing::{info, warn};
+
+use crate::{error::AppError, local, CustomConfig};
+
+// Defaults of `--max-retries` and `--retry-backoff-ms`
+pub const MAX_RETRIES: u32 = 5;
+pub const RETRY_BACKOFF: Duration = Duration::from_millis(500);
+
+// An `OnlineClient` that connects again when the node goes away, e.g. when the
+// local node is restarted mid-run. Operations run through `retry`, which on a
+// connection error waits, reconnects to the same url and runs the operation
+// again, doubling the wait each time, up to `max_retries` times.
+pub struct ReconnectingClient {
+    url: Option<String>,
+    api: RwLock<OnlineClient<CustomConfig>>,
+    max_retries: u32,
+    backoff: Duration,
+    genesis_hash: Option<H256>,
+}
+
+impl ReconnectingClient {
+    pub async fn connect(url: &str, max_retries: u32, backoff: Duration) -> Result<Self, AppError> {
+        validate_url(url)?;
+        let api = with_backoff(max_retries, backoff, is_connection_error, || async {
+            OnlineClient::<CustomConfig>::from_url(url)
+                .await
+                .map_err(|source| AppError::Connection { url: url.to_string(), source })
+        })
+        .await?;
+        check_connection(&api).await?;
+
+        Ok(Self {
+            url: Some(url.to_string()),
+            api: RwLock::new(api),
+            max_retries,
+            backoff,
+            genesis_hash: None,
+        })
+    }
+
+    // Wraps an already connected client, which can't be reconnected as its
+    // url isn't known, so operations are only run once
+    pub fn from_client(api: OnlineClient<CustomConfig>) -> Self {
+        Self {
+            url: None,
+            api: RwLock::new(api),
+            max_retries: 0,
+            backoff: RETRY_BACKOFF,
+            genesis_hash: None,
+        }
+    }
+
+    // Signs against `genesis_hash` instead of the node's, like
+    // `--genesis-hash`, including on the clients of later reconnections
+    pub fn with_genesis_hash(mut self, genesis_hash: H256) -> Self {
+        self.api.get_mut().unwrap().set_genesis_hash(genesis_hash);
+        self.genesis_hash = Some(genesis_hash);
+        self
+    }
+
+    // The client currently connected
+    pub fn api(&self) -> OnlineClient<CustomConfig> {
+        self.api.read().unwrap().clone()
+    }
+
+    // Runs `op` with the current client, reconnecting and running it again
+    // when it fails because the connection was lost
+    pub async fn retry<T, F, Fut>(&self, mut op: F) -> Result<T, AppError>
+    where
+        F: FnMut(OnlineClient<CustomConfig>) -> Fut,
+        Fut: Future<Output = Result<T, AppError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            match op(self.api()).await {
+                Err(e) if attempt < self.max_retries && is_connection_error(&e) => {
+                    let Some(url) = &self.url else {
+                        return Err(e);
+                    };
+                    attempt += 1;
+                    let wait = self.backoff * 2u32.saturating_pow(attempt - 1);
+                    warn!(attempt, retries = self.max_retries, ?wait, "Lost the connection to {url} ({e}), reconnecting");
+                    tokio::time::sleep(wait).await;
+                    self.reconnect().await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    // Connects to the url again and replaces the current client, which is
+    // kept when that fails. Without a url there's nothing to reconnect to.
+    pub async fn reconnect(&self) {
+        let Some(url) = &self.url else {
+            return;
+        };
+
+        match OnlineClient::<CustomConfig>::from_url(url).await {
+            Ok(api) => {
+                if let Some(genesis_hash) = self.genesis_hash {
+                    api.set_genesis_hash(genesis_hash);
+                }
+                *self.api.write().unwrap() = api;
+            }
+            Err(e) => warn!("Could not reconnect to {url}: {e}"),
+        }
+    }
+}
+
+// Runs `op` until it succeeds or fails with an error `is_transient` doesn't
+// accept, waiting `backoff` before the first retry and twice as long before
+// each of the next ones, up to `max_retries` retries
+pub async fn with_backoff<T, E, F, Fut>(
+    max_retries: u32,
+    backoff: Duration,
+    is_transient: impl Fn(&E) -> bool,
+    mut op: F,
+) -> Result<T, E>
+where
+    E: std::fmt::Display,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Err(e) if attempt < max_retries && is_transient(&e) => {
+                attempt += 1;
+                let wait = backoff * 2u32.saturating_pow(attempt - 1);
+                warn!(attempt, retries = max_retries, ?wait, "{e}, retrying");
+                tokio::time::sleep(wait).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+// Rejects what isn't a `ws://` or `wss://` url before trying to connect to it.
+// Plain `ws://` is only accepted by subxt for local nodes, anything public has
+// to go through `wss://`.
+pub fn validate_url(url: &str) -> Result<(), AppError> {
+    let invalid = |reason: String| AppError::Connection {
+        url: url.to_string(),
+        source: subxt::Error::Other(reason),
+    };
+
+    let parsed = url::Url::parse(url).map_err(|e| invalid(format!("not a valid url ({e})")))?;
+    if !matches!(parsed.scheme(), "ws" | "wss") {
+        return Err(invalid(format!("unsupported scheme `{}`, use ws:// or wss://", parsed.scheme())));
+    }
+    if parsed.host_str().is_none_or(str::is_empty) {
+        return Err(invalid("the url has no host".into()));
+    }
+
+    Ok(())
+}
+
+// Logs which network the client ended up on, to catch pointing at the wrong
+// endpoint early. The runtime's `Core_version` fails like any other call when
+// the connection isn't usable.
+pub async fn check_connection(api: &OnlineClient<CustomConfig>) -> Result<(), AppError> {
+    let version = api
+        .runtime_api()
+        .at_latest()
+        .await?
+        .call(local::apis().core().version())
+        .await?;
+
+    info!(
+        genesis = ?api.genesis_hash(),
+        spec_name = version.spec_name,
+        spec_version = version.spec_version,
+        "Connected"
+    );
+    Ok(())
+}
+
+// Chain types, as `system_chainType` reports them, of the chains the example
+// is meant for and submits to without `--yes-i-know`
+const LOCAL_CHAIN_TYPES: [&str; 2] = ["Development", "Local"];
+
+// Why the submissions of the run are refused, see `SubmitPolicy`, when the
+// node doesn't report a development or local chain, as creating assets or
+// spending funds there is likely a mistake. `acknowledged` (`--yes-i-know`)
+// lets them through anyway, e.g. for a chopsticks fork of a live chain.
+pub async fn guard_live_chain(rpc: &RpcClient, acknowledged: bool) -> Option<String> {
+    let chain_type = rpc
+        .request::<serde_json::Value>("system_chainType", rpc_params![])
+        .await
+        .map(|chain_type| chain_type.as_str().map_or_else(|| chain_type.to_string(), str::to_string));
+    let chain = rpc.request::<String>("system_chain", rpc_params![]).await.unwrap_or_default();
+
+    match chain_type {
+        Ok(chain_type) if LOCAL_CHAIN_TYPES.contains(&chain_type.as_str()) => None,
+        _ if acknowledged => {
+            warn!(chain, "Submitting to a chain that isn't a local one, as acknowledged");
+            None
+        }
+        chain_type => {
+            let chain_type = chain_type.unwrap_or_else(|e| format!("unknown ({e})"));
+            Some(format!("{chain} is a {chain_type} chain, not a local one; pass --yes-i-know to submit to it anyway"))
+        }
+    }
+}
+
+// Whether the error comes from the connection to the node rather than from
+// the node or the runtime answering
+pub fn is_connection_error(error: &AppError) -> bool {
+    match error {
+        AppError::Connection { source, .. } | AppError::Subxt(source) | AppError::FeeEstimation(source) => {
+            is_transport_error(source)
+        }
+        _ => false,
+    }
+}
+
+pub fn is_transport_error(error: &subxt::Error) -> bool {
+    matches!(
+        error,
+        subxt::Error::Io(_)
+            | subxt::Error::Rpc(
+                RpcError::ClientError(_) | RpcError::SubscriptionDropped | RpcError::DisconnectedWillReconnect(_)
+            )
+    )
+}
@@ -0,0 +1,78 @@
+use codec::Decode;
+use subxt::{tx::Signer, OnlineClient};
+use tracing::{info, warn};
+
+use crate::{
+    cli::BatchMode, describe::describe_batch, dry_run_batch_calls, error::AppError, sign_and_send_batch_calls, Call, CustomConfig,
+    MortalityConfig, SubmitPolicy,
+};
+
+// A run log lists the batches a run submitted, each one as the hex encoded
+// (SCALE) `RuntimeCall`s it contained:
+//
+//   { "batches": [["0x3200...", "0x3211..."], ["0x3801..."]] }
+#[derive(serde::Deserialize)]
+struct RunLog {
+    batches: Vec<Vec<String>>,
+}
+
+// Decodes a recorded call with the current metadata. Calls recorded against a
+// runtime whose call indices or arguments have since changed don't decode.
+fn decode_call(call: &str) -> Result<Call, AppError> {
+    let bytes = hex::decode(call.strip_prefix("0x").unwrap_or(call)).map_err(|e| AppError::Invalid(e.to_string()))?;
+    let mut input = bytes.as_slice();
+    let decoded = Call::decode(&mut input).map_err(|e| AppError::Invalid(e.to_string()))?;
+
+    if !input.is_empty() {
+        return Err(AppError::Invalid(format!("{} trailing bytes", input.len())));
+    }
+
+    Ok(decoded)
+}
+
+// Re-submits the batches of a run log in order, signed by the current signer
+// with fresh nonces, to reproduce the environment the run left behind. Calls
+// that no longer decode are skipped with a warning, and a batch left empty is
+// skipped altogether. With `validate_only` each batch is validated instead of
+// submitted. A batch relying on what an earlier one did is rejected then, so
+// that's reported and the next one is validated.
+pub async fn replay<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    path: &str,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+) -> Result<(), AppError> {
+    let file_error = |reason: String| AppError::File { path: path.to_string(), reason };
+    let contents = std::fs::read_to_string(path).map_err(|e| file_error(e.to_string()))?;
+    let log: RunLog = serde_json::from_str(&contents).map_err(|e| file_error(e.to_string()))?;
+
+    for (index, batch) in log.batches.iter().enumerate() {
+        let mut calls = Vec::new();
+        for call in batch {
+            match decode_call(call) {
+                Ok(decoded) => calls.push(decoded),
+                Err(e) => warn!("Skipping a call of batch {index} that is not valid anymore ({e}): {call}"),
+            }
+        }
+
+        if calls.is_empty() {
+            warn!("Batch {index} has no valid calls left, skipping it");
+            continue;
+        }
+
+        info!(index, "Replaying batch {index}:\n{}", describe_batch(&calls, BatchMode::All));
+        if validate_only {
+            match dry_run_batch_calls(&api, signer, calls, tip, mortality, BatchMode::All).await {
+                Ok(()) => info!(index, "Dry run: the batch would be accepted"),
+                Err(e) => warn!(index, "Dry run: the batch would be rejected: {e}"),
+            }
+            continue;
+        }
+        sign_and_send_batch_calls(api.clone(), signer, policy, calls, tip, mortality, BatchMode::All).await?;
+    }
+
+    Ok(())
+}
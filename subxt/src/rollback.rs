@@ -0,0 +1,59 @@
+use subxt::{blocks::ExtrinsicEvents, tx::Signer, OnlineClient};
+use tracing::{error, info, warn};
+
+use crate::{cli::BatchMode, local, sign_and_send_batch_calls, AssetsCall, Call, CustomConfig, MortalityConfig, SubmitPolicy};
+
+// Which steps of the setup made it on chain, from the events of the
+// transactions that went through
+#[derive(Debug, Default)]
+pub struct SetupReport {
+    pub asset_created: bool,
+    pub pool_created: bool,
+    pub liquidity_added: bool,
+}
+
+impl SetupReport {
+    pub fn record(&mut self, events: &ExtrinsicEvents<CustomConfig>) -> Result<(), subxt::Error> {
+        self.asset_created |= events.has::<local::assets::events::Created>()?;
+        self.pool_created |= events.has::<local::asset_conversion::events::PoolCreated>()?;
+        self.liquidity_added |= events.has::<local::asset_conversion::events::LiquidityAdded>()?;
+        Ok(())
+    }
+}
+
+// Undoes what a failed setup left behind and reports it. An asset it created
+// is destroyed, which requires the signer to still be its owner. Pools can't
+// be removed in `pallet-asset-conversion`, so a created pool stays, and
+// neither does liquidity ever need removing, as adding it is the last step.
+pub async fn rollback_setup<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    asset_id: u32,
+    report: &SetupReport,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+) {
+    if report.pool_created {
+        warn!(asset_id, "Rollback: the pool can't be removed and is left in place");
+    }
+
+    if !report.asset_created {
+        info!("Rollback: nothing to roll back");
+        return;
+    }
+
+    // Destroying an asset goes through these steps in order; the accounts
+    // and approvals are removed in bulk, which is enough for a fresh asset
+    let calls = vec![
+        Call::Assets(AssetsCall::start_destroy { id: asset_id }),
+        Call::Assets(AssetsCall::destroy_accounts { id: asset_id }),
+        Call::Assets(AssetsCall::destroy_approvals { id: asset_id }),
+        Call::Assets(AssetsCall::finish_destroy { id: asset_id }),
+    ];
+
+    match sign_and_send_batch_calls(api.clone(), signer, policy, calls, tip, mortality, BatchMode::All).await {
+        Ok(_) => info!(asset_id, "Rollback: destroyed the asset"),
+        Err(e) => error!(asset_id, "Rollback: could not destroy the asset: {e}"),
+    }
+}
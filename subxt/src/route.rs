@@ -0,0 +1,176 @@
+use subxt::OnlineClient;
+
+use crate::{
+    amm::{get_amount_in, get_amount_out, spot_price},
+    error::AppError,
+    asset_decimals, local, local_asset_location, location_decimals, native_location,
+    units::format_units,
+    CustomConfig, MultiLocation, CURVE_STEPS,
+};
+
+// Quotes a swap of `amount_in` along an explicit path of assets by chaining
+// the reserves of every hop's pool with the pallet's swap math, instead of
+// letting the runtime pick the route. Returns `None` when a hop has no pool
+// or no liquidity.
+pub async fn quote_path(
+    api: &OnlineClient<CustomConfig>,
+    path: &[MultiLocation],
+    amount_in: u128,
+) -> Result<Option<u128>, AppError> {
+    let runtime_api = api.runtime_api().at_latest().await?;
+    let lp_fee = api.constants().at(&local::constants().asset_conversion().lp_fee())?;
+
+    let mut amount = amount_in;
+    for hop in path.windows(2) {
+        let reserves = local::apis().asset_conversion_api().get_reserves(hop[0].clone(), hop[1].clone());
+        let Some((reserve_in, reserve_out)) = runtime_api.call(reserves).await? else {
+            return Ok(None);
+        };
+
+        let Some(amount_out) = get_amount_out(amount, reserve_in, reserve_out, lp_fee) else {
+            return Ok(None);
+        };
+        amount = amount_out;
+    }
+
+    Ok(Some(amount))
+}
+
+// The reverse of `quote_path`: what has to go in at the start of the path for
+// the swap to get exactly `amount_out` at its end, walking the hops backwards.
+// Returns `None` when a hop has no pool or not enough liquidity.
+pub async fn quote_path_exact_out(
+    api: &OnlineClient<CustomConfig>,
+    path: &[MultiLocation],
+    amount_out: u128,
+) -> Result<Option<u128>, AppError> {
+    let runtime_api = api.runtime_api().at_latest().await?;
+    let lp_fee = api.constants().at(&local::constants().asset_conversion().lp_fee())?;
+
+    let mut amount = amount_out;
+    for hop in path.windows(2).rev() {
+        let reserves = local::apis().asset_conversion_api().get_reserves(hop[0].clone(), hop[1].clone());
+        let Some((reserve_in, reserve_out)) = runtime_api.call(reserves).await? else {
+            return Ok(None);
+        };
+
+        let Some(amount_in) = get_amount_in(amount, reserve_in, reserve_out, lp_fee) else {
+            return Ok(None);
+        };
+        amount = amount_in;
+    }
+
+    Ok(Some(amount))
+}
+
+// Prints the quote along the explicit path next to the runtime's own quote
+// between the path's ends, to see how the forced route compares with the one
+// the runtime picks. Amounts are displayed with the decimals of the path's
+// ends.
+pub async fn compare_path_quote(
+    api: &OnlineClient<CustomConfig>,
+    path: &[MultiLocation],
+    amount_in: u128,
+    native_decimals: u8,
+) -> Result<(), AppError> {
+    let [first, .., last] = path else {
+        return Err(AppError::Invalid("the path needs at least two assets".into()));
+    };
+
+    let path_quote = quote_path(api, path, amount_in).await?;
+
+    let runtime_quote = local::apis().asset_conversion_api().quote_price_exact_tokens_for_tokens(
+        first.clone(),
+        last.clone(),
+        amount_in,
+        true,
+    );
+    let runtime_quote = api.runtime_api().at_latest().await?.call(runtime_quote).await?;
+
+    let decimals_in = location_decimals(api, first, native_decimals).await;
+    let decimals_out = location_decimals(api, last, native_decimals).await;
+    let out = |quote: Option<u128>| quote.map_or_else(|| "none".to_string(), |quote| format_units(quote, decimals_out));
+
+    println!("\nQuote of {} along a {}-hop path:", format_units(amount_in, decimals_in), path.len() - 1);
+    println!("  explicit path   {}", out(path_quote));
+    println!("  runtime route   {}", out(runtime_quote));
+
+    if let (Some(path_quote), Some(runtime_quote)) = (path_quote, runtime_quote) {
+        match path_quote.cmp(&runtime_quote) {
+            std::cmp::Ordering::Greater => {
+                println!("  the explicit path gets {} more", format_units(path_quote - runtime_quote, decimals_out))
+            }
+            std::cmp::Ordering::Less => {
+                println!("  the runtime route gets {} more", format_units(runtime_quote - path_quote, decimals_out))
+            }
+            std::cmp::Ordering::Equal => println!("  both routes get the same amount"),
+        }
+    }
+
+    Ok(())
+}
+
+// Quotes swapping each of `amounts` of the native token for the asset at
+// `asset`, all at the same block. How the output per input shrinks as the
+// amounts grow shows the pool's depth.
+pub async fn price_curve(
+    api: &OnlineClient<CustomConfig>,
+    asset: MultiLocation,
+    amounts: Vec<u128>,
+) -> Result<Vec<(u128, Option<u128>)>, AppError> {
+    let runtime_api = api.runtime_api().at_latest().await?;
+
+    let mut curve = Vec::with_capacity(amounts.len());
+    for amount in amounts {
+        let quote = local::apis().asset_conversion_api().quote_price_exact_tokens_for_tokens(
+            native_location(),
+            asset.clone(),
+            amount,
+            true,
+        );
+        curve.push((amount, runtime_api.call(quote).await?));
+    }
+
+    Ok(curve)
+}
+
+// The `price-curve` subcommand: the curve of the native/asset pool from
+// `amount` up, `CURVE_STEPS` amounts each ten times the previous one
+pub async fn print_price_curve(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    amount: u128,
+    native_decimals: u8,
+    plain: bool,
+) -> Result<(), AppError> {
+    let amounts = (0..CURVE_STEPS).filter_map(|step| amount.checked_mul(10u128.pow(step))).collect();
+    let asset_decimals = asset_decimals(api, asset_id).await?;
+    let curve = price_curve(api, local_asset_location(asset_id), amounts).await?;
+    print!("{}", format_curve(&curve, native_decimals, asset_decimals, plain));
+    Ok(())
+}
+
+// Renders a price curve with the effective rate (output per input, in whole
+// units) of every amount, either as an aligned table or as tab separated
+// values
+pub fn format_curve(curve: &[(u128, Option<u128>)], native_decimals: u8, asset_decimals: u8, plain: bool) -> String {
+    let rows = curve.iter().map(|(amount, quote)| {
+        let rate = quote.and_then(|quote| spot_price(*amount, native_decimals, quote, asset_decimals));
+        (
+            format_units(*amount, native_decimals),
+            quote.map_or("-".to_string(), |quote| format_units(quote, asset_decimals)),
+            rate.map_or("-".to_string(), |rate| rate.to_string()),
+        )
+    });
+
+    if plain {
+        return rows.map(|(amount, quote, rate)| format!("{amount}\t{quote}\t{rate}\n")).collect();
+    }
+
+    let mut table = format!("{:>20}  {:>20}  {}\n", "amount in", "amount out", "rate");
+    for (amount, quote, rate) in rows {
+        table.push_str(&format!("{amount:>20}  {quote:>20}  {rate}\n"));
+    }
+
+    table
+}
@@ -0,0 +1,725 @@
+use subxt::{
+    blocks::ExtrinsicEvents,
+    tx::Signer,
+    utils::{AccountId32, MultiAddress},
+    OnlineClient,
+};
+use subxt_signer::sr25519::dev;
+use tracing::{info, warn};
+
+use crate::{
+    add_liquidity_call,
+    amm::{minimal_reserves, LiquidityPreview},
+    apply_slippage, asset_balance, asset_pair,
+    cli::{AssetConfig, AssetSpec, BatchMode, Cli, SetupParams},
+    client::{AssetHubClient, BorrowedSigner},
+    convert_fees, create_asset_calls, create_pool_call, create_pool_with_native_call,
+    cost::{total_setup_cost, SetupCost},
+    describe::describe_batch,
+    dry_run_batch_calls,
+    error::{classify_dispatch_error, AppError},
+    estimate_fees,
+    fee::estimate_fee_both_units,
+    free_balance,
+    funding::fund_signer,
+    liquidity::preview_liquidity,
+    local, local_asset_location, native_location, per_mille, pool_exists, provide_liquidity_to_token_native_pool_call,
+    rollback::{rollback_setup, SetupReport},
+    set_team_call, sign_and_send_batch_calls,
+    units::format_units,
+    Call, CustomConfig, MortalityConfig, MultiLocation, SubmissionReceipt, SubmitPolicy, LIQUIDITY_BUFFER,
+    SWAP_SLIPPAGE, TRANSFER_AMOUNT,
+};
+
+// The least liquidity the native/custom asset pool needs to quote a fee of
+// `expected_fee` native plancks, with the fee swap moving the price by at
+// most `slippage` per mille, plus a `LIQUIDITY_BUFFER` margin. Returns the
+// native and asset amounts to provide.
+async fn minimal_liquidity_for_fees(
+    api: &OnlineClient<CustomConfig>,
+    expected_fee: u128,
+    slippage: u128,
+) -> Result<(u128, u128), AppError> {
+    let constants = api.constants();
+    let existential_deposit = constants.at(&local::constants().balances().existential_deposit())?;
+    let lp_fee = constants.at(&local::constants().asset_conversion().lp_fee())?;
+    let mint_min_liquidity = constants.at(&local::constants().asset_conversion().mint_min_liquidity())?;
+
+    let (native, asset) = minimal_reserves(expected_fee, slippage, existential_deposit, lp_fee, mint_min_liquidity)
+        .ok_or_else(|| AppError::Setup("could not size the liquidity for this fee and slippage".into()))?;
+
+    let buffered = |amount: u128| per_mille(amount, 1000 + LIQUIDITY_BUFFER, "the buffered liquidity");
+    Ok((buffered(native)?, buffered(asset)?))
+}
+
+// Compares the predicted liquidity provision with the `LiquidityAdded` event
+// actually emitted. A divergence above 0.1% usually means the reserves weren't
+// what we assumed, or the amounts were off by some decimals.
+fn report_liquidity(
+    predicted: LiquidityPreview,
+    events: &ExtrinsicEvents<CustomConfig>,
+    native_decimals: u8,
+    asset_decimals: u8,
+) -> Result<(), AppError> {
+    let Some(added) = events.find_first::<local::asset_conversion::events::LiquidityAdded>()? else {
+        warn!("No `LiquidityAdded` event found to compare the prediction with");
+        return Ok(());
+    };
+
+    for (label, predicted, actual, decimals) in [
+        ("native provided", predicted.amount1, added.amount1_provided, native_decimals),
+        ("asset provided", predicted.amount2, added.amount2_provided, asset_decimals),
+        ("LP tokens minted", predicted.lp_minted, added.lp_token_minted, 0),
+    ] {
+        info!(
+            predicted = %format_units(predicted, decimals),
+            actual = %format_units(actual, decimals),
+            "Liquidity provision, {label}"
+        );
+        if predicted.abs_diff(actual).saturating_mul(1000) > predicted.max(actual) {
+            warn!("{label} diverges from the prediction by more than 0.1%");
+        }
+    }
+
+    Ok(())
+}
+
+// Whether the pallet rejected an `add_liquidity` because one side fell below
+// its `amountN_min`, which happens when the reserves move between the quote
+// and the inclusion
+fn is_liquidity_slippage(err: &AppError) -> bool {
+    use local::runtime_types::pallet_asset_conversion::pallet::Error as PalletError;
+
+    let AppError::Subxt(subxt::Error::Runtime(subxt::error::DispatchError::Module(module_error))) = err else {
+        return false;
+    };
+    matches!(
+        module_error.as_root_error::<local::Error>(),
+        Ok(local::Error::AssetConversion(
+            PalletError::AssetOneDepositDidNotMeetMinimum | PalletError::AssetTwoDepositDidNotMeetMinimum
+        ))
+    )
+}
+
+// Adds liquidity to the native/custom asset pool with the minimums set the
+// slippage tolerance below what the current reserves call for. When someone
+// else trades in between and the pallet rejects the provision, the amounts are
+// recomputed from the new reserves and it's tried again, up to
+// `liquidity_retries` times.
+async fn add_liquidity_with_retries<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    asset_id: u32,
+    amount1_desired: u128,
+    amount2_desired: u128,
+    options: &SetupOptions,
+) -> Result<SubmissionReceipt, AppError> {
+    let (retries, tip, mortality) = (options.liquidity_retries, options.tip, options.mortality);
+    let mut attempt = 0;
+    loop {
+        let preview = preview_liquidity(&api, asset_id, amount1_desired, amount2_desired)
+            .await?
+            .ok_or_else(|| AppError::Setup("the pool would reject the liquidity at its current price".into()))?;
+
+        let call = provide_liquidity_to_token_native_pool_call(
+            asset_id,
+            amount1_desired,
+            amount2_desired,
+            apply_slippage(preview.amount1, options.slippage_bps)?,
+            apply_slippage(preview.amount2, options.slippage_bps)?,
+            signer.account_id(),
+        );
+
+        match sign_and_send_batch_calls(api.clone(), signer, &options.submit, vec![call], tip, mortality, BatchMode::All).await {
+            Err(e) if is_liquidity_slippage(&e) => {
+                if attempt == retries {
+                    return Err(AppError::Setup(format!(
+                        "the pool price kept moving, gave up adding liquidity after {retries} retries: {e}"
+                    )));
+                }
+                attempt += 1;
+                warn!("The pool price moved ({e}), retrying the liquidity provision ({attempt}/{retries})");
+            }
+            result => return result,
+        }
+    }
+}
+
+// Accounts taking over the asset's roles once the setup has minted it
+struct Team {
+    issuer: AccountId32,
+    admin: AccountId32,
+    freezer: AccountId32,
+}
+
+// How the setup gets submitted
+pub struct SetupOptions {
+    // Print the setup batch and ask before signing it
+    confirm: bool,
+    // Predict the liquidity provision and compare it with the actual one
+    verbose: bool,
+    // Add the liquidity in a second transaction, after the pool is created
+    staged: bool,
+    // Distinct issuer/admin/freezer accounts, if any
+    team: Option<Team>,
+    // Undo the steps that went through when a later one fails
+    rollback: bool,
+    // Account topping the signer up before the setup, if any
+    funder: Option<subxt_signer::sr25519::Keypair>,
+    // Native plancks tipped on every setup transaction
+    tip: u128,
+    // Era of every setup transaction, immortal when unset
+    mortality: Option<MortalityConfig>,
+    // Whether a failing call of the setup batch reverts the others
+    batch_mode: BatchMode,
+    // Times a staged liquidity provision is retried when the price moves
+    liquidity_retries: u32,
+    // Seed only the liquidity needed to quote fees
+    minimal_liquidity: bool,
+    // Tolerance below the liquidity amounts the pool may take, in basis points
+    slippage_bps: u16,
+    // Assets created next to ours, each with a pool with it
+    extra_assets: Vec<AssetSpec>,
+    // Retries and refusal of the setup's submissions
+    submit: SubmitPolicy,
+}
+
+// Logs the setup for review, see `describe_setup`, and asks whether it should
+// be submitted. The prompt goes to stderr too, leaving stdout to the results.
+fn confirm_setup(description: &str) -> bool {
+    info!("The setup is about to be signed:\n{description}");
+    eprint!("Submit it? [y/N] ");
+    let _ = std::io::Write::flush(&mut std::io::stderr());
+
+    let mut answer = String::new();
+    if std::io::stdin().read_line(&mut answer).is_err() {
+        return false;
+    }
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+// Creates what the setup needs and is missing, through the client's
+// `ensure_asset` and `ensure_pool`: our asset, handed over to the team once
+// it's minted, the other assets, whose roles stay with the signer, and the
+// pools between them. Records in `report` what was created.
+async fn ensure_setup<S: Signer<CustomConfig>>(
+    client: &AssetHubClient<S>,
+    asset: &AssetSpec,
+    options: &SetupOptions,
+    report: &mut SetupReport,
+) -> Result<(), AppError> {
+    report.asset_created = client.ensure_asset(asset).await?;
+    if !report.asset_created {
+        info!(asset_id = asset.id, "Not minting to an existing asset");
+        if options.team.is_some() {
+            info!("Skipping set_team too, the roles of an existing asset are left as they are");
+        }
+    } else if let Some(team) = &options.team {
+        // The roles are handed over once the signer is done minting, as
+        // minting needs the issuer role
+        client.set_team(asset.id, team.issuer.clone(), team.admin.clone(), team.freezer.clone()).await?;
+    }
+
+    for spec in &options.extra_assets {
+        client.ensure_asset(spec).await?;
+    }
+
+    report.pool_created = client.ensure_pool(native_location(), local_asset_location(asset.id)).await?;
+    for spec in &options.extra_assets {
+        let (asset1, asset2) = asset_pair(asset.id, spec.id);
+        client.ensure_pool(asset1, asset2).await?;
+    }
+    Ok(())
+}
+
+// The setup's liquidity, added once the pools exist: each other asset's pool
+// with ours gets `liquidity_asset` of both, and the native pool the given
+// amounts. The native pool's is always the last call, see the staged setup.
+fn liquidity_calls(
+    asset_id: u32,
+    params: &SetupParams,
+    options: &SetupOptions,
+    address: &AccountId32,
+    (liquidity_native, liquidity_asset): (u128, u128),
+) -> Result<Vec<Call>, AppError> {
+    let mut calls = Vec::new();
+    for spec in &options.extra_assets {
+        let (asset1, asset2) = asset_pair(asset_id, spec.id);
+        let amount = params.liquidity_asset;
+        let min = apply_slippage(amount, options.slippage_bps)?;
+        calls.push(add_liquidity_call(asset1, asset2, (amount, amount), (min, min), address.clone()));
+    }
+
+    calls.push(provide_liquidity_to_token_native_pool_call(
+        asset_id,
+        liquidity_native,
+        liquidity_asset,
+        apply_slippage(liquidity_native, options.slippage_bps)?,
+        apply_slippage(liquidity_asset, options.slippage_bps)?,
+        address.clone(),
+    ));
+    Ok(calls)
+}
+
+// Everything the setup would submit on a chain that has none of it yet,
+// what `ensure_setup` creates followed by the liquidity batch, e.g. to
+// estimate what the setup costs at most
+fn setup_calls_from_scratch(asset: &AssetSpec, options: &SetupOptions, address: &AccountId32, liquidity: &[Call]) -> Vec<Call> {
+    let mut calls = create_asset_calls(asset, address);
+    if let Some(team) = &options.team {
+        calls.push(set_team_call(asset.id, team.issuer.clone().into(), team.admin.clone().into(), team.freezer.clone().into()));
+    }
+    for spec in &options.extra_assets {
+        calls.extend(create_asset_calls(spec, address));
+    }
+
+    calls.push(create_pool_with_native_call(asset.id));
+    for spec in &options.extra_assets {
+        let (asset1, asset2) = asset_pair(asset.id, spec.id);
+        calls.push(create_pool_call(asset1, asset2));
+    }
+    calls.extend_from_slice(liquidity);
+    calls
+}
+
+// Renders what the setup is about to do for review: the assets and pools it
+// creates unless they exist, then its liquidity batch
+fn describe_setup(plan: &SetupPlan, options: &SetupOptions) -> String {
+    let asset = &plan.asset;
+    let mut description = String::from("Unless they already exist, creates:\n");
+    for spec in std::iter::once(asset).chain(&options.extra_assets) {
+        description.push_str(&format!(
+            "  the asset {} ({}, {} decimals), minting {} to the signer\n",
+            spec.id, spec.symbol, spec.decimals, spec.mint_amount
+        ));
+    }
+    if options.team.is_some() {
+        description.push_str(&format!("  then hands the roles of the asset {} over to the team\n", asset.id));
+    }
+    description.push_str(&format!("  the pool of the asset {} with the native token\n", asset.id));
+    for spec in &options.extra_assets {
+        description.push_str(&format!("  the pool of the asset {} with the asset {}\n", asset.id, spec.id));
+    }
+
+    description.push_str("Then submits:\n");
+    description.push_str(&describe_batch(&plan.calls, options.batch_mode));
+    description
+}
+
+// Submits the setup's liquidity calls as one batch, or as two transactions
+// for a staged setup, recording in `report` what the first one did.
+// The staged setup submits the native pool's `add_liquidity` call on its own
+// once the rest is finalized. The second transaction is only signed after
+// that, so it picks up the signer's updated nonce, and its minimums are
+// set from the reserves at that point, retried when the price moves.
+async fn submit_setup<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    mut call_buffer: Vec<Call>,
+    asset_id: u32,
+    (liquidity_native, liquidity_asset): (u128, u128),
+    options: &SetupOptions,
+    report: &mut SetupReport,
+) -> Result<SubmissionReceipt, AppError> {
+    if !options.staged {
+        return sign_and_send_batch_calls(api.clone(), signer, &options.submit, call_buffer, options.tip, options.mortality, options.batch_mode).await;
+    }
+
+    call_buffer.pop();
+    if !call_buffer.is_empty() {
+        let receipt = sign_and_send_batch_calls(api.clone(), signer, &options.submit, call_buffer, options.tip, options.mortality, options.batch_mode).await?;
+        if let Err(e) = report.record(&receipt.events) {
+            warn!("Could not read the setup events: {e}");
+        }
+    }
+    info!("Pool ready, adding the liquidity in a second transaction");
+    add_liquidity_with_retries(api.clone(), signer, asset_id, liquidity_native, liquidity_asset, options).await
+}
+
+// What the setup creates and submits: our asset, the liquidity batch added
+// once the assets and pools exist with the liquidity it seeds the native pool
+// with, and what the setup is estimated to cost, when that could be estimated
+struct SetupPlan {
+    asset: AssetSpec,
+    calls: Vec<Call>,
+    liquidity: (u128, u128),
+    // The fee a minimal liquidity was sized to quote
+    expected_fee: Option<u128>,
+    cost: Option<SetupCost>,
+}
+
+// Sizes the liquidity, builds the setup's liquidity calls and estimates what
+// the setup costs, warning when the signer can't afford it (or won't be, once
+// the funder tops it up). Nothing is submitted.
+async fn plan_setup<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    options: &SetupOptions,
+    native_token: &(u8, String),
+) -> Result<SetupPlan, AppError> {
+    let (native_decimals, native_symbol) = native_token;
+    let address: AccountId32 = signer.account_id();
+
+    // A minimal setup only seeds what the pool needs to quote the fee of the
+    // example transfer, instead of the amounts of `params`
+    let mut expected_fee = None;
+    let liquidity = if options.minimal_liquidity {
+        let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+        let fee = estimate_fees(api.clone(), dest, TRANSFER_AMOUNT).await?;
+        expected_fee = Some(fee);
+        let (native, asset) = minimal_liquidity_for_fees(api, fee, SWAP_SLIPPAGE).await?;
+        info!(native, asset, asset_id = asset_config.id, "Seeding the minimal liquidity");
+        (native, asset)
+    } else {
+        (params.liquidity_native, params.liquidity_asset)
+    };
+
+    let asset = AssetSpec::new(asset_config, params);
+    let calls = liquidity_calls(asset.id, params, options, &address, liquidity)?;
+
+    let all_calls = setup_calls_from_scratch(&asset, options, &address, &calls);
+    let cost = match total_setup_cost(api, asset_config, &all_calls, liquidity.0).await {
+        Ok(cost) => {
+            info!(fee = cost.tx_fee, calls = all_calls.len(), "Estimated the fee of the setup");
+            match free_balance(api, address).await {
+                Ok(balance) if balance < cost.total() && options.funder.is_none() => warn!(
+                    "The signer has {} {native_symbol} but the setup needs {} {native_symbol}",
+                    format_units(balance, *native_decimals),
+                    format_units(cost.total(), *native_decimals)
+                ),
+                Ok(_) => {}
+                Err(e) => warn!("Could not read the signer's balance: {e}"),
+            }
+            Some(cost)
+        }
+        Err(e) => {
+            warn!("Could not estimate the setup cost: {e}");
+            None
+        }
+    };
+
+    Ok(SetupPlan {
+        asset,
+        calls,
+        liquidity,
+        expected_fee,
+        cost,
+    })
+}
+
+// Prints what the setup does and costs, and validates its liquidity batch
+// against the node without submitting anything. The assets and pools can't be
+// validated ahead of their creation, so they're only described. Only a batch
+// that can't be validated at all is an error, one the node would reject is
+// reported as such.
+pub async fn dry_run_setup<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    options: &SetupOptions,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    let plan = plan_setup(api, signer, asset_config, params, options, native_token).await?;
+    if let Some(cost) = &plan.cost {
+        info!("{}", cost.summary(native_token.0, &native_token.1));
+    }
+
+    info!("Dry run, the setup is not submitted:\n{}", describe_setup(&plan, options));
+    match dry_run_batch_calls(api, signer, plan.calls, options.tip, options.mortality, options.batch_mode).await {
+        Ok(()) => info!("Dry run: the setup batch would be accepted"),
+        Err(e) => warn!("Dry run: the setup batch would be rejected: {e}"),
+    }
+    Ok(())
+}
+
+// We use this to setup the stage for our transfer, using the calls defined earlier
+// to create our custom asset, set it's metadata, mint it, create the liquidity pool
+// and provide liquidity to it. What's missing is created first, see
+// `ensure_setup`, then the liquidity is added as a batch, or as two batches
+// for a staged setup, see `submit_setup`.
+// The signer becomes the asset's admin and receives the minted tokens and the
+// liquidity pool tokens. Returns the receipt of the setup's last transaction,
+// and fails when it didn't go through or, with `confirm`, wasn't confirmed.
+// See `dry_run_setup` to only validate it.
+pub async fn prepare_setup<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    options: &SetupOptions,
+    native_token: &(u8, String),
+) -> Result<SubmissionReceipt, AppError> {
+    let (native_decimals, native_symbol) = native_token;
+    let asset_id = asset_config.id;
+
+    // Preflight: what the setup is going to cost, see `plan_setup`
+    let plan = plan_setup(&api, signer, asset_config, params, options, native_token).await?;
+    if options.verbose || options.confirm {
+        if let Some(cost) = &plan.cost {
+            info!("{}", cost.summary(*native_decimals, native_symbol));
+        }
+    }
+
+    if options.confirm && !confirm_setup(&describe_setup(&plan, options)) {
+        return Err(AppError::Refused("the setup wasn't confirmed".into()));
+    }
+    let SetupPlan {
+        asset,
+        calls: call_buffer,
+        liquidity,
+        expected_fee,
+        cost,
+    } = plan;
+    let (liquidity_native, liquidity_asset) = liquidity;
+
+    if let Some(funder) = &options.funder {
+        let cost = cost.ok_or_else(|| {
+            AppError::Setup("not funding the signer without knowing what the setup costs".into())
+        })?;
+        fund_signer(&api, funder, &options.submit, signer.account_id(), cost.total(), native_token).await?;
+    }
+
+    // In verbose mode we predict the liquidity provision up front, to compare
+    // it with what the runtime actually did once the batch is in
+    let predicted = if options.verbose {
+        match preview_liquidity(&api, asset_id, liquidity_native, liquidity_asset).await {
+            Ok(predicted) => predicted,
+            Err(e) => {
+                warn!("Could not preview the liquidity provision: {e}");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // The setup mints to the signer, which shows in its asset balance
+    let symbol = &asset_config.symbol;
+    let balance_before = asset_balance(&api, asset_id, &signer.account_id()).await?;
+    info!(
+        balance = balance_before,
+        symbol,
+        display = format_units(balance_before, asset_config.decimals),
+        "Asset balance before the setup"
+    );
+
+    // The client creates what's missing, each step finalized before the
+    // next. The liquidity batch is only signed after that, so it picks up
+    // the signer's nonce past the client's transactions.
+    let client = AssetHubClient::new(api.clone(), BorrowedSigner(signer))
+        .with_submit_policy(options.submit.clone())
+        .with_tip(options.tip)
+        .with_mortality(options.mortality);
+    let mut report = SetupReport::default();
+    let result = match ensure_setup(&client, &asset, options, &mut report).await {
+        Ok(()) => submit_setup(&api, signer, call_buffer, asset_id, liquidity, options, &mut report).await,
+        Err(e) => Err(e),
+    };
+
+    // The setup goes through in several transactions, so a failing one can
+    // leave the steps of the earlier ones behind
+    if result.is_err() && options.rollback {
+        rollback_setup(&api, signer, &options.submit, asset_id, &report, options.tip, options.mortality).await;
+    }
+
+    // A dispatch error means the runtime rejected the setup, which callers
+    // may want to tell apart from not reaching the node
+    let receipt = result.map_err(|e| match e {
+        AppError::Subxt(subxt::Error::Runtime(dispatch_error)) => AppError::Setup(format!(
+            "the runtime rejected the setup: {}",
+            classify_dispatch_error(&dispatch_error, &api.metadata())
+        )),
+        e => e,
+    })?;
+
+    let balance_after = asset_balance(&api, asset_id, &signer.account_id()).await?;
+    let minted = if report.asset_created { params.mint_amount } else { 0 };
+    info!(
+        balance = balance_after,
+        minted,
+        symbol,
+        display = format_units(balance_after, asset_config.decimals),
+        "Asset balance after the setup, minted less the liquidity provided"
+    );
+
+    if let Some(predicted) = predicted {
+        if let Err(e) = report_liquidity(predicted, &receipt.events, *native_decimals, asset_config.decimals) {
+            warn!("Could not compare the liquidity provision: {e}");
+        }
+    }
+
+    // The whole point of the minimal liquidity is that fees can be quoted
+    if let Some(fee) = expected_fee {
+        match convert_fees(api.clone(), asset_id, fee).await {
+            Ok(converted) => info!(
+                fee_plancks = fee,
+                fee_in_asset = converted,
+                asset_id,
+                display = format_units(converted, asset_config.decimals),
+                "The pool quotes the fee in the asset"
+            ),
+            Err(e) => warn!("The minimal liquidity doesn't let the pool quote fees: {e}"),
+        }
+    }
+
+    Ok(receipt)
+}
+
+// The setup as the command line configures it. Roles that aren't given stay
+// with the signer, and there's no team to set when none is given. The
+// metadata is checked against the chain's limit up front, as an oversized
+// name would only fail once the batch is dispatched.
+pub fn from_cli<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    cli: &Cli,
+    asset_id: u32,
+    tip: u128,
+) -> Result<(AssetConfig, SetupParams, SetupOptions), AppError> {
+    let team = if cli.issuer.is_some() || cli.admin.is_some() || cli.freezer.is_some() {
+        Some(Team {
+            issuer: cli.issuer.clone().unwrap_or_else(|| signer.account_id()),
+            admin: cli.admin.clone().unwrap_or_else(|| signer.account_id()),
+            freezer: cli.freezer.clone().unwrap_or_else(|| signer.account_id()),
+        })
+    } else {
+        None
+    };
+
+    let asset_config = AssetConfig {
+        id: asset_id,
+        name: cli.name.clone(),
+        symbol: cli.symbol.clone(),
+        decimals: cli.decimals,
+    };
+    let string_limit = api.constants().at(&local::constants().assets().string_limit())?;
+    asset_config.validate(string_limit).map_err(AppError::Setup)?;
+    for spec in &cli.extra_assets {
+        spec.config().validate(string_limit).map_err(AppError::Setup)?;
+    }
+    let params = SetupParams::from_cli(cli);
+    params.validate()?;
+    params.validate_extra_assets(asset_id, &cli.extra_assets)?;
+    let extra_assets = cli
+        .extra_assets
+        .iter()
+        .map(|spec| AssetSpec {
+            min_balance: params.min_balance,
+            ..spec.clone()
+        })
+        .collect();
+
+    let options = SetupOptions {
+        confirm: cli.confirm,
+        verbose: cli.verbose,
+        staged: cli.staged_setup,
+        team,
+        rollback: cli.rollback_on_failure,
+        funder: cli.funder.clone(),
+        tip,
+        mortality: cli.mortal_period,
+        batch_mode: cli.batch_mode,
+        liquidity_retries: cli.liquidity_retries,
+        minimal_liquidity: cli.minimal_liquidity,
+        slippage_bps: cli.slippage_bps,
+        extra_assets,
+        submit: policy.clone(),
+    };
+    Ok((asset_config, params, options))
+}
+
+// The `setup` subcommand: the setup alone, validated instead of submitted
+// with `--dry-run`
+pub async fn run<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    options: &SetupOptions,
+    dry_run: bool,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    if dry_run {
+        return dry_run_setup(&api, signer, asset_config, params, options, native_token).await;
+    }
+    let receipt = prepare_setup(api, signer, asset_config, params, options, native_token).await?;
+    info!(block = ?receipt.block_hash, extrinsic = ?receipt.extrinsic_hash, "Setup finalized");
+    Ok(())
+}
+
+// The setup as a plain `cargo run` submits it: untipped and immortal, with
+// the liquidity in one atomic batch, signed by `signer`, which also receives
+// the minted tokens and LP tokens. Used to prepare a chain from outside the
+// example's flow, e.g. by the integration tests.
+pub async fn setup<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    native_token: &(u8, String),
+) -> Result<SubmissionReceipt, AppError> {
+    let options = SetupOptions {
+        confirm: false,
+        verbose: false,
+        staged: false,
+        team: None,
+        rollback: false,
+        funder: None,
+        tip: 0,
+        mortality: None,
+        batch_mode: BatchMode::All,
+        liquidity_retries: 0,
+        minimal_liquidity: false,
+        slippage_bps: 50,
+        extra_assets: Vec::new(),
+        submit: SubmitPolicy::default(),
+    };
+
+    prepare_setup(api, signer, asset_config, params, &options, native_token).await
+}
+
+// Only quotes the fee of the example transfer in the custom asset, without
+// submitting it. The setup is submitted first when the pool doesn't exist
+// yet, and skipped when it does, so this can be used as a fee oracle against
+// an already prepared chain. A dry run doesn't create the pool, so without
+// the pool there's no fee to quote. The estimate is printed as JSON with
+// `--json`.
+pub async fn estimate_only<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    cli: &Cli,
+    asset_config: &AssetConfig,
+    params: &SetupParams,
+    options: &SetupOptions,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    let asset_id = asset_config.id;
+    let native: MultiLocation = native_location();
+    let asset: MultiLocation = local_asset_location(asset_id);
+
+    if !pool_exists(api.clone(), native, asset).await? {
+        if cli.dry_run {
+            dry_run_setup(&api, signer, asset_config, params, options, native_token).await?;
+            return Err(AppError::Setup("the pool doesn't exist, and a dry run doesn't create it".into()));
+        }
+        let receipt = prepare_setup(api.clone(), signer, asset_config, params, options, native_token).await?;
+        info!(block = ?receipt.block_hash, extrinsic = ?receipt.extrinsic_hash, "Setup finalized");
+    }
+
+    let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+
+    let estimate = estimate_fee_both_units(&api, asset_id, dest, TRANSFER_AMOUNT, native_token).await?;
+
+    if cli.json {
+        println!("{}", estimate.to_json());
+    } else {
+        println!("\n{estimate}\n");
+    }
+
+    Ok(())
+}
+
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use sp_core::{ecdsa as sp_ecdsa, ed25519, hashing::blake2_256, sr25519 as sp_sr25519, Pair};
+use subxt::{
+    config::ExtrinsicParams,
+    tx::{Payload, Signer, SubmittableExtrinsic},
+    utils::{AccountId32, MultiAddress, MultiSignature},
+    OnlineClient,
+};
+use subxt_signer::{ecdsa, sr25519, SecretUri};
+
+use crate::{
+    cli::KeyType,
+    error::{check_signed_extensions, AppError},
+    CustomConfig, WestmintExtrinsicParams,
+};
+
+// A key of any of the schemes Substrate accounts use. `CustomConfig` signs
+// with a `MultiSignature`, which has a variant for each of them, so they all
+// fit the same config. The account id of an ecdsa key is the blake2-256 hash
+// of its compressed public key, the other two use the public key as is.
+pub enum AnySigner {
+    Sr25519(sr25519::Keypair),
+    // subxt-signer doesn't implement ed25519, sp-core does
+    Ed25519(Box<ed25519::Pair>),
+    Ecdsa(ecdsa::Keypair),
+}
+
+impl AnySigner {
+    // Derives the key of `key_type` from a secret URI (mnemonic or `0x` seed,
+    // with optional derivation path). Ed25519 and ecdsa keys only support
+    // hard junctions (`//`).
+    pub fn from_uri(uri: &str, key_type: KeyType) -> Result<Self, AppError> {
+        let invalid = |e: String| AppError::Signer(format!("invalid secret URI for a {key_type:?} key: {e}"));
+
+        Ok(match key_type {
+            KeyType::Sr25519 => Self::Sr25519(crate::signer_from_secret_uri(uri)?),
+            KeyType::Ed25519 => {
+                let pair = ed25519::Pair::from_string(uri, None).map_err(|e| invalid(format!("{e:?}")))?;
+                Self::Ed25519(Box::new(pair))
+            }
+            KeyType::Ecdsa => {
+                let uri = SecretUri::from_str(uri).map_err(|e| invalid(e.to_string()))?;
+                Self::Ecdsa(ecdsa::Keypair::from_uri(&uri).map_err(|e| invalid(e.to_string()))?)
+            }
+        })
+    }
+}
+
+impl Signer<CustomConfig> for AnySigner {
+    fn account_id(&self) -> AccountId32 {
+        match self {
+            Self::Sr25519(keypair) => Signer::<CustomConfig>::account_id(keypair),
+            Self::Ed25519(pair) => AccountId32(pair.public().0),
+            Self::Ecdsa(keypair) => Signer::<CustomConfig>::account_id(keypair),
+        }
+    }
+
+    fn address(&self) -> MultiAddress<AccountId32, ()> {
+        Signer::<CustomConfig>::account_id(self).into()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> MultiSignature {
+        match self {
+            Self::Sr25519(keypair) => Signer::<CustomConfig>::sign(keypair, signer_payload),
+            Self::Ed25519(pair) => MultiSignature::Ed25519(pair.sign(signer_payload).0),
+            Self::Ecdsa(keypair) => Signer::<CustomConfig>::sign(keypair, signer_payload),
+        }
+    }
+}
+
+// Signs `call` like `create_signed` does, but checks the signature against
+// the signer's account before putting the extrinsic together. A signer that
+// can't fail the signing itself, e.g. the Ledger one, is then reported as an
+// `AppError::Signer` instead of submitting an extrinsic the pool rejects.
+pub async fn create_verified<S: Signer<CustomConfig>>(
+    api: &OnlineClient<CustomConfig>,
+    call: &impl Payload,
+    signer: &S,
+    params: <WestmintExtrinsicParams as ExtrinsicParams<CustomConfig>>::Params,
+) -> Result<SubmittableExtrinsic<CustomConfig, OnlineClient<CustomConfig>>, AppError> {
+    let account_id = signer.account_id();
+    let partial = api
+        .tx()
+        .create_partial_signed(call, &account_id, params)
+        .await
+        .map_err(check_signed_extensions)?;
+
+    let signer_payload = partial.signer_payload();
+    let signature = signer.sign(&signer_payload);
+    if !signature_matches(&signature, &signer_payload, &account_id) {
+        return Err(AppError::Signer(format!("the signature doesn't match the account {account_id}")));
+    }
+
+    Ok(partial.sign_with_address_and_signature(&signer.address(), &signature))
+}
+
+// Whether `signature` is the account's signature of `message`. The public key
+// of an ecdsa account is recovered from the signature and hashed, see
+// `AnySigner`.
+pub(crate) fn signature_matches(signature: &MultiSignature, message: &[u8], account_id: &AccountId32) -> bool {
+    match signature {
+        MultiSignature::Ed25519(signature) => {
+            ed25519::Pair::verify(&ed25519::Signature::from_raw(*signature), message, &ed25519::Public::from_raw(account_id.0))
+        }
+        MultiSignature::Sr25519(signature) => {
+            sp_sr25519::Pair::verify(&sp_sr25519::Signature::from_raw(*signature), message, &sp_sr25519::Public::from_raw(account_id.0))
+        }
+        MultiSignature::Ecdsa(signature) => sp_ecdsa::Signature::from_raw(*signature)
+            .recover(message)
+            .is_some_and(|public| blake2_256(public.as_ref()) == account_id.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use codec::{Decode, Encode};
+    use subxt_signer::sr25519::dev;
+
+    use super::*;
+
+    // Every scheme signs into the `MultiSignature` of `CustomConfig`, which
+    // the runtime checks against the signer's account id
+    #[test]
+    fn signs_with_each_key_type() {
+        use sp_runtime::traits::Verify;
+
+        let payload = b"asset conversion example";
+        for key_type in [KeyType::Sr25519, KeyType::Ed25519, KeyType::Ecdsa] {
+            let signer = AnySigner::from_uri("//Alice", key_type).unwrap();
+            let signature = sp_runtime::MultiSignature::decode(&mut &signer.sign(payload).encode()[..]).unwrap();
+            let account = sp_runtime::AccountId32::new(signer.account_id().0);
+
+            assert!(signature.verify(&payload[..], &account), "{key_type:?}");
+        }
+    }
+
+    // A signature only matches the account that made it, over the payload it
+    // was made for, whatever the key type
+    #[test]
+    fn signature_verification() {
+        for key_type in [KeyType::Sr25519, KeyType::Ed25519, KeyType::Ecdsa] {
+            let signer = AnySigner::from_uri("//Alice", key_type).unwrap();
+            let account_id = Signer::<CustomConfig>::account_id(&signer);
+            let signature = Signer::<CustomConfig>::sign(&signer, b"payload");
+
+            assert!(signature_matches(&signature, b"payload", &account_id), "{key_type:?}");
+            assert!(!signature_matches(&signature, b"other payload", &account_id), "{key_type:?}");
+            assert!(!signature_matches(&signature, b"payload", &dev::bob().public_key().into()), "{key_type:?}");
+        }
+        let alice = dev::alice().public_key().into();
+        assert!(!signature_matches(&subxt::utils::MultiSignature::Ed25519([0; 64]), b"payload", &alice));
+    }
+}
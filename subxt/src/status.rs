@@ -0,0 +1,240 @@
+use subxt::{utils::{AccountId32, MultiAddress}, OnlineClient};
+use subxt_signer::sr25519::dev;
+
+use crate::{
+    amm::spot_price,
+    asset_balance, asset_decimals,
+    convert_fees,
+    error::AppError,
+    estimate_fees, free_balance, local, local_asset_location, native_location, pool_exists,
+    units::format_units, value_in_asset, CustomConfig, TRANSFER_AMOUNT,
+};
+
+// The `status` subcommand: the node, the finalized block and the asset's
+// metadata and balance, then the readiness checks, printed as a table, tab
+// separated values or JSON
+pub async fn run(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    account: AccountId32,
+    native_token: &(u8, String),
+    json: bool,
+    plain: bool,
+) -> Result<(), AppError> {
+    let mut checks = gather_environment(api, asset_id, &account).await?;
+    checks.extend(gather_status(api, asset_id, account, native_token).await?);
+    if json {
+        println!("{}", format_json(&checks));
+    } else if plain {
+        print!("{}", format_plain(&checks));
+    } else {
+        print!("{}", format_table(&checks));
+    }
+    Ok(())
+}
+
+// One line of the readiness report
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub value: String,
+}
+
+impl Check {
+    fn new(name: &'static str, ok: bool, value: impl Into<String>) -> Self {
+        Check {
+            name,
+            ok,
+            value: value.into(),
+        }
+    }
+}
+
+// Runs the read-only checks telling whether the example transfer can pay its
+// fee with the custom asset: the asset exists, its pool with the native token
+// exists and has liquidity (and at which spot price), and the fee can be
+// quoted in the asset. The account's native balance is valued in the asset
+// too. A failing check doesn't stop the following ones.
+pub async fn gather_status(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    account: AccountId32,
+    native_token: &(u8, String),
+) -> Result<Vec<Check>, AppError> {
+    let (native_decimals, native_symbol) = native_token;
+    let storage = api.storage().at_latest().await?;
+    let mut checks = Vec::new();
+    // Amounts of the asset are displayed with the decimals of its metadata
+    let asset_decimals = asset_decimals(api, asset_id).await?;
+
+    let details = storage.fetch(&local::storage().assets().asset(asset_id)).await?;
+    checks.push(match details {
+        Some(details) => Check::new(
+            "asset exists",
+            true,
+            format!("{asset_id} (supply {})", format_units(details.supply, asset_decimals)),
+        ),
+        None => Check::new("asset exists", false, format!("no asset {asset_id}")),
+    });
+
+    let pool = pool_exists(api.clone(), native_location(), local_asset_location(asset_id)).await?;
+    checks.push(Check::new("pool exists", pool, if pool { "native/asset" } else { "missing" }));
+
+    let reserves = local::apis()
+        .asset_conversion_api()
+        .get_reserves(native_location(), local_asset_location(asset_id));
+    let (native_reserve, asset_reserve) = api
+        .runtime_api()
+        .at_latest()
+        .await?
+        .call(reserves)
+        .await?
+        .unwrap_or((0, 0));
+    checks.push(match (native_reserve, asset_reserve) {
+        (0, _) | (_, 0) => Check::new("liquidity", false, "no reserves"),
+        (native, asset) => Check::new(
+            "liquidity",
+            true,
+            format!(
+                "{} {native_symbol} / {}",
+                format_units(native, *native_decimals),
+                format_units(asset, asset_decimals)
+            ),
+        ),
+    });
+
+    // The price of one native token in the asset, e.g. `1 WND = 0.001 asset`
+    checks.push(match spot_price(native_reserve, *native_decimals, asset_reserve, asset_decimals) {
+        Some(price) => Check::new("spot price", true, format!("1 {native_symbol} = {price} asset {asset_id}")),
+        None => Check::new("spot price", false, "no reserves"),
+    });
+
+    let balance = free_balance(api, account.clone()).await?;
+    let value = value_in_asset(api, balance, local_asset_location(asset_id)).await?;
+    checks.push(Check::new(
+        "balance",
+        balance > 0,
+        match value {
+            Some(value) => format!(
+                "{account} has {} {native_symbol}, worth {} of asset {asset_id}",
+                format_units(balance, *native_decimals),
+                format_units(value, asset_decimals)
+            ),
+            None => format!(
+                "{account} has {} {native_symbol}, too much for the pool to value",
+                format_units(balance, *native_decimals)
+            ),
+        },
+    ));
+
+    let dest: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+    let fee = match estimate_fees(api.clone(), dest, TRANSFER_AMOUNT).await {
+        Ok(fee) => match convert_fees(api.clone(), asset_id, fee).await {
+            Ok(converted_fee) => Check::new(
+                "fee estimate",
+                true,
+                format!(
+                    "{} {native_symbol} = {} of asset {asset_id}",
+                    format_units(fee, *native_decimals),
+                    format_units(converted_fee, asset_decimals)
+                ),
+            ),
+            Err(e) => Check::new("fee estimate", false, e.to_string()),
+        },
+        Err(e) => Check::new("fee estimate", false, e.to_string()),
+    };
+    checks.push(fee);
+
+    Ok(checks)
+}
+
+// The rows the `status` subcommand shows before the readiness checks: which
+// runtime the node runs, how far it has finalized, the asset's metadata and
+// what the account holds of it
+pub async fn gather_environment(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    account: &AccountId32,
+) -> Result<Vec<Check>, AppError> {
+    let mut checks = Vec::new();
+
+    let version = api
+        .runtime_api()
+        .at_latest()
+        .await?
+        .call(local::apis().core().version())
+        .await?;
+    checks.push(Check::new(
+        "node",
+        true,
+        format!("{} v{}", version.spec_name, version.spec_version),
+    ));
+
+    let finalized = api.blocks().at_latest().await?;
+    checks.push(Check::new(
+        "finalized block",
+        true,
+        format!("#{} ({:?})", finalized.number(), finalized.hash()),
+    ));
+
+    let metadata = local::storage().assets().metadata(asset_id);
+    let metadata = api.storage().at_latest().await?.fetch(&metadata).await?;
+    checks.push(match metadata {
+        Some(metadata) => Check::new(
+            "asset metadata",
+            true,
+            format!(
+                "{} ({}), {} decimals",
+                String::from_utf8_lossy(&metadata.name.0),
+                String::from_utf8_lossy(&metadata.symbol.0),
+                metadata.decimals
+            ),
+        ),
+        None => Check::new("asset metadata", false, "not set"),
+    });
+
+    let balance = asset_balance(api, asset_id, account).await?;
+    let decimals = asset_decimals(api, asset_id).await?;
+    checks.push(Check::new(
+        "asset balance",
+        balance > 0,
+        format!("{account} has {} of asset {asset_id}", format_units(balance, decimals)),
+    ));
+
+    Ok(checks)
+}
+
+// Renders the checks as an aligned table with a ✓/✗ per check
+pub fn format_table(checks: &[Check]) -> String {
+    let width = checks.iter().map(|check| check.name.len()).max().unwrap_or(0).max("check".len());
+
+    let mut table = format!("{:<width$}  ok  value\n", "check");
+    table.push_str(&format!("{}  --  {}\n", "-".repeat(width), "-".repeat(5)));
+    for check in checks {
+        let mark = if check.ok { "✓" } else { "✗" };
+        table.push_str(&format!("{:<width$}  {mark}   {}\n", check.name, check.value));
+    }
+
+    table
+}
+
+// Renders the checks as tab separated `name ok value` lines, for scripts
+pub fn format_plain(checks: &[Check]) -> String {
+    checks
+        .iter()
+        .map(|check| format!("{}\t{}\t{}\n", check.name, check.ok, check.value))
+        .collect()
+}
+
+pub fn format_json(checks: &[Check]) -> serde_json::Value {
+    checks
+        .iter()
+        .map(|check| {
+            serde_json::json!({
+                "check": check.name,
+                "ok": check.ok,
+                "value": check.value,
+            })
+        })
+        .collect()
+}
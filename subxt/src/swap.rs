@@ -0,0 +1,218 @@
+use subxt::{tx::Signer, OnlineClient};
+use tracing::{info, warn};
+
+use crate::{
+    amm, apply_slippage, apply_slippage_above, check_amount,
+    cli::{BatchMode, Cli},
+    dry_run_batch_calls,
+    error::AppError,
+    find_events, local, local_asset_location, location_decimals, native_location,
+    route::{quote_path, quote_path_exact_out},
+    sign_and_send_batch_calls, swap_exact_tokens_for_tokens_call, swap_tokens_for_exact_tokens_call,
+    units::format_units,
+    CustomConfig, MortalityConfig, MultiLocation, SubmitPolicy,
+};
+
+// The `swap` subcommand: `amount` along `path`, native to the asset when it's
+// empty, for an exact input or with `exact_out` for an exact output. It's
+// tipped, signed and validated instead with `--dry-run` like the example's
+// own transactions.
+#[allow(clippy::too_many_arguments)]
+pub async fn run<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    cli: &Cli,
+    asset_id: u32,
+    amount: u128,
+    path: &[MultiLocation],
+    min_out: Option<u128>,
+    exact_out: bool,
+    tip: u128,
+    native_decimals: u8,
+) -> Result<(), AppError> {
+    let path = match path {
+        [] => vec![native_location(), local_asset_location(asset_id)],
+        path => path.to_vec(),
+    };
+
+    if exact_out {
+        swap_for_exact_along_path(api, signer, policy, path, amount, cli.slippage_bps, tip, cli.mortal_period, cli.dry_run, native_decimals, cli.slippage_warn_bps).await
+    } else {
+        swap_along_path(api, signer, policy, path, amount, min_out, cli.slippage_bps, cli.min_amount, tip, cli.mortal_period, cli.dry_run, native_decimals, cli.slippage_warn_bps).await
+    }
+}
+
+// Swaps `amount_in` of the first asset of `path` for its last one, hop by
+// hop, accepting down to `min_out`, or to `slippage_bps` less than quoted
+// when it isn't given. Once finalized the
+// `SwapExecuted` event tells what was actually received, which is checked
+// against the minimum. The runtime should never pay out less than that, so
+// doing it anyway is reported as an error.
+#[allow(clippy::too_many_arguments)]
+pub async fn swap_along_path<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    path: Vec<MultiLocation>,
+    amount_in: u128,
+    min_out: Option<u128>,
+    slippage_bps: u16,
+    min_amount: u128,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+    native_decimals: u8,
+    warn_bps: u16,
+) -> Result<(), AppError> {
+    check_amount(amount_in, min_amount)?;
+    if path.len() < 2 {
+        return Err(AppError::Swap("the swap path needs at least two assets".into()));
+    }
+    let decimals_in = location_decimals(&api, &path[0], native_decimals).await;
+    let decimals_out = location_decimals(&api, &path[path.len() - 1], native_decimals).await;
+
+    let quoted = quote_path(&api, &path, amount_in)
+        .await?
+        .ok_or_else(|| AppError::Swap("the pools along the path could not quote the swap".into()))?;
+    let amount_out_min = match min_out {
+        Some(min_out) => min_out,
+        None => apply_slippage(quoted, slippage_bps)?,
+    };
+
+    let hops = path.len() - 1;
+    let call = swap_exact_tokens_for_tokens_call(path, amount_in, amount_out_min, signer.account_id(), true);
+    if validate_only {
+        info!(
+            amount_in,
+            hops,
+            quoted,
+            minimum = amount_out_min,
+            display_in = format_units(amount_in, decimals_in),
+            display_quoted = format_units(quoted, decimals_out),
+            "Dry run of the swap"
+        );
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api, signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let swaps = find_events::<local::asset_conversion::events::SwapExecuted>(&events)?;
+    let [swap] = swaps.as_slice() else {
+        return Err(AppError::Swap("expected exactly one `SwapExecuted` event".into()));
+    };
+
+    info!(
+        amount_in,
+        hops,
+        quoted,
+        minimum = amount_out_min,
+        actual = swap.amount_out,
+        display_in = format_units(amount_in, decimals_in),
+        display_out = format_units(swap.amount_out, decimals_out),
+        "Swap executed"
+    );
+    report_slippage((amount_in, quoted), swap, (decimals_in, decimals_out), warn_bps);
+
+    if swap.amount_out < amount_out_min {
+        return Err(AppError::Swap(format!(
+            "the swap paid out {} which is below the minimum of {amount_out_min}",
+            swap.amount_out
+        )));
+    }
+
+    Ok(())
+}
+
+// Swaps along `path` for exactly `amount_out` of its last asset, spending up
+// to `slippage_bps` more of the first one than quoted. The runtime enforces
+// that maximum, but the `SwapExecuted` event is checked against it anyway.
+#[allow(clippy::too_many_arguments)]
+pub async fn swap_for_exact_along_path<S: Signer<CustomConfig>>(
+    api: OnlineClient<CustomConfig>,
+    signer: &S,
+    policy: &SubmitPolicy,
+    path: Vec<MultiLocation>,
+    amount_out: u128,
+    slippage_bps: u16,
+    tip: u128,
+    mortality: Option<MortalityConfig>,
+    validate_only: bool,
+    native_decimals: u8,
+    warn_bps: u16,
+) -> Result<(), AppError> {
+    if path.len() < 2 {
+        return Err(AppError::Swap("the swap path needs at least two assets".into()));
+    }
+    let decimals_in = location_decimals(&api, &path[0], native_decimals).await;
+    let decimals_out = location_decimals(&api, &path[path.len() - 1], native_decimals).await;
+
+    let quoted = quote_path_exact_out(&api, &path, amount_out)
+        .await?
+        .ok_or_else(|| AppError::Swap("the pools along the path could not quote the swap".into()))?;
+    let amount_in_max = apply_slippage_above(quoted, slippage_bps)?;
+
+    let hops = path.len() - 1;
+    let call = swap_tokens_for_exact_tokens_call(path, amount_out, amount_in_max, signer.account_id(), true);
+    if validate_only {
+        info!(
+            amount_out,
+            hops,
+            quoted,
+            maximum = amount_in_max,
+            display_out = format_units(amount_out, decimals_out),
+            display_quoted = format_units(quoted, decimals_in),
+            "Dry run of the swap for an exact amount"
+        );
+        return dry_run_batch_calls(&api, signer, vec![call], tip, mortality, BatchMode::All).await;
+    }
+    let events = sign_and_send_batch_calls(api, signer, policy, vec![call], tip, mortality, BatchMode::All).await?.events;
+
+    let swaps = find_events::<local::asset_conversion::events::SwapExecuted>(&events)?;
+    let [swap] = swaps.as_slice() else {
+        return Err(AppError::Swap("expected exactly one `SwapExecuted` event".into()));
+    };
+
+    info!(
+        amount_out,
+        hops,
+        quoted,
+        maximum = amount_in_max,
+        actual = swap.amount_in,
+        display_out = format_units(amount_out, decimals_out),
+        display_in = format_units(swap.amount_in, decimals_in),
+        "Swap for an exact amount executed"
+    );
+    report_slippage((quoted, amount_out), swap, (decimals_in, decimals_out), warn_bps);
+
+    if swap.amount_in > amount_in_max {
+        return Err(AppError::SwapInputExceeded {
+            consumed: swap.amount_in,
+            max: amount_in_max,
+        });
+    }
+
+    Ok(())
+}
+
+// Logs the price a swap got, the output per unit of input from its
+// `SwapExecuted` event, against the quoted one, warning when it's more than
+// `warn_bps` basis points worse
+fn report_slippage(
+    (quoted_in, quoted_out): (u128, u128),
+    swap: &local::asset_conversion::events::SwapExecuted,
+    (decimals_in, decimals_out): (u8, u8),
+    warn_bps: u16,
+) {
+    let quoted_price = amm::spot_price(quoted_in, decimals_in, quoted_out, decimals_out);
+    let realized_price = amm::spot_price(swap.amount_in, decimals_in, swap.amount_out, decimals_out);
+    let Some(bps) = amm::slippage_bps(quoted_in, quoted_out, swap.amount_in, swap.amount_out) else {
+        warn!(quoted_in, quoted_out, actual_in = swap.amount_in, actual_out = swap.amount_out, "Could not compute the slippage of the swap");
+        return;
+    };
+
+    let slippage = format!("{:.2}%", bps as f64 / 100.0);
+    info!(?quoted_price, ?realized_price, slippage, "Realized price of the swap");
+    if bps > warn_bps.into() {
+        warn!(slippage, threshold_bps = warn_bps, "The swap got a worse price than quoted beyond the threshold");
+    }
+}
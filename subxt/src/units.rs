@@ -0,0 +1,71 @@
+use subxt::backend::legacy::LegacyRpcMethods;
+use tracing::warn;
+
+use crate::CustomConfig;
+
+// Used when the node doesn't report its token properties, which are the ones
+// of Westend Asset Hub
+const DEFAULT_NATIVE_DECIMALS: u8 = 12;
+const DEFAULT_NATIVE_SYMBOL: &str = "WND";
+
+// Reads the native token's decimals and symbol from the chain properties
+// (`tokenDecimals` and `tokenSymbol` of the `system_properties` RPC). Chains
+// with several tokens report arrays, in which case the first one is native.
+pub async fn native_token_info(rpc: &LegacyRpcMethods<CustomConfig>) -> (u8, String) {
+    let Ok(properties) = rpc.system_properties().await else {
+        warn!("Could not read the chain properties, assuming {DEFAULT_NATIVE_SYMBOL} with {DEFAULT_NATIVE_DECIMALS} decimals");
+        return (DEFAULT_NATIVE_DECIMALS, DEFAULT_NATIVE_SYMBOL.to_string());
+    };
+
+    let first = |value: &serde_json::Value| match value {
+        serde_json::Value::Array(values) => values.first().cloned(),
+        value => Some(value.clone()),
+    };
+
+    let decimals = properties
+        .get("tokenDecimals")
+        .and_then(first)
+        .and_then(|decimals| decimals.as_u64())
+        .and_then(|decimals| u8::try_from(decimals).ok())
+        .unwrap_or(DEFAULT_NATIVE_DECIMALS);
+
+    let symbol = properties
+        .get("tokenSymbol")
+        .and_then(first)
+        .and_then(|symbol| symbol.as_str().map(str::to_string))
+        .unwrap_or_else(|| DEFAULT_NATIVE_SYMBOL.to_string());
+
+    (decimals, symbol)
+}
+
+// Renders a raw integer amount in units of a token with `decimals` decimals,
+// e.g. `1500000000000` with 12 decimals is `1.5`. Works on the digits rather
+// than dividing by `10^decimals`, which doesn't fit a `u128` past 38 decimals.
+pub fn format_units(raw: u128, decimals: u8) -> String {
+    let decimals = usize::from(decimals);
+    let digits = format!("{raw:0>width$}", width = decimals + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals);
+
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{whole}.{fraction}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amounts_in_units() {
+        assert_eq!(format_units(12_340_000_000_000, 12), "12.34");
+        assert_eq!(format_units(0, 12), "0");
+        assert_eq!(format_units(5, 12), "0.000000000005");
+        assert_eq!(format_units(1_000_000_000_000, 12), "1");
+        assert_eq!(format_units(42, 0), "42");
+        assert_eq!(format_units(u128::MAX, 0), u128::MAX.to_string());
+        assert_eq!(format_units(u128::MAX, 18), "340282366920938463463.374607431768211455");
+        assert_eq!(format_units(u128::MAX, 39), "0.340282366920938463463374607431768211455");
+        assert_eq!(format_units(1, 255), format!("0.{}1", "0".repeat(254)));
+    }
+}
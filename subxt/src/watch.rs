@@ -0,0 +1,131 @@
+use std::cell::Cell;
+
+use codec::Encode;
+use subxt::{blocks::Block, OnlineClient};
+use tracing::{info, warn};
+
+use crate::{
+    error::AppError,
+    local::asset_conversion::events,
+    local_asset_id, location_decimals, native_location,
+    units::format_units,
+    CustomConfig, MultiLocation,
+};
+
+// Prints the `AssetConversion` events of the finalized blocks as they come,
+// one line each, until the subscription ends or fails. `last` is the number of
+// the last block printed, which lets a new subscription pick up where the
+// previous one stopped: the blocks finalized in between are walked back to by
+// their parents and printed first, so a reconnection doesn't skip any.
+// Amounts are displayed with the decimals of the token they're in, LP tokens
+// as they are.
+pub(crate) async fn watch_finalized(
+    api: OnlineClient<CustomConfig>,
+    last: &Cell<Option<u64>>,
+    native_decimals: u8,
+) -> Result<(), AppError> {
+    let mut blocks = api.blocks().subscribe_finalized().await?;
+    info!("Watching the finalized blocks for AssetConversion events");
+
+    while let Some(block) = blocks.next().await {
+        let block = block?;
+        let number: u64 = block.number().into();
+
+        let mut missed = Vec::new();
+        if let Some(last) = last.get() {
+            let mut parent = block.header().parent_hash;
+            for _ in last + 1..number {
+                let block = api.blocks().at(parent).await?;
+                parent = block.header().parent_hash;
+                missed.push(block);
+            }
+            if !missed.is_empty() {
+                warn!(from = last + 1, to = number - 1, "Catching up on the blocks finalized while disconnected");
+            }
+        }
+
+        for block in missed.into_iter().rev().chain([block]) {
+            print_events(&api, &block, native_decimals).await?;
+            last.set(Some(block.number().into()));
+        }
+    }
+
+    Ok(())
+}
+
+async fn print_events(
+    api: &OnlineClient<CustomConfig>,
+    block: &Block<CustomConfig, OnlineClient<CustomConfig>>,
+    native_decimals: u8,
+) -> Result<(), subxt::Error> {
+    let number = block.number();
+    let amount = |amount: u128, location: &MultiLocation| {
+        let location = location.clone();
+        async move { format_units(amount, location_decimals(api, &location, native_decimals).await) }
+    };
+
+    for event in block.events().await?.iter() {
+        let event = event?;
+        let line = if let Some(e) = event.as_event::<events::PoolCreated>()? {
+            format!(
+                "PoolCreated pool={} creator={} account={} lp_token={}",
+                pool(&e.pool_id),
+                e.creator,
+                e.pool_account,
+                e.lp_token
+            )
+        } else if let Some(e) = event.as_event::<events::LiquidityAdded>()? {
+            format!(
+                "LiquidityAdded pool={} who={} amounts={}/{} lp_minted={}",
+                pool(&e.pool_id),
+                e.who,
+                amount(e.amount1_provided, &e.pool_id.0).await,
+                amount(e.amount2_provided, &e.pool_id.1).await,
+                e.lp_token_minted
+            )
+        } else if let Some(e) = event.as_event::<events::LiquidityRemoved>()? {
+            format!(
+                "LiquidityRemoved pool={} who={} amounts={}/{} lp_burned={} fee={}",
+                pool(&e.pool_id),
+                e.who,
+                amount(e.amount1, &e.pool_id.0).await,
+                amount(e.amount2, &e.pool_id.1).await,
+                e.lp_token_burned,
+                e.withdrawal_fee.0
+            )
+        } else if let Some(e) = event.as_event::<events::SwapExecuted>()? {
+            let path: Vec<_> = e.path.iter().map(|(location, _)| label(location)).collect();
+            let (Some((first, _)), Some((last, _))) = (e.path.first(), e.path.last()) else {
+                continue;
+            };
+            format!(
+                "SwapExecuted path={} who={} in={} out={}",
+                path.join(">"),
+                e.who,
+                amount(e.amount_in, first).await,
+                amount(e.amount_out, last).await
+            )
+        } else {
+            continue;
+        };
+        println!("#{number} {line}");
+    }
+
+    Ok(())
+}
+
+fn pool((asset1, asset2): &(MultiLocation, MultiLocation)) -> String {
+    format!("{}/{}", label(asset1), label(asset2))
+}
+
+// `native`, `asset <id>` for the local assets, or the location itself
+fn label(location: &MultiLocation) -> String {
+    if location.encode() == native_location::<MultiLocation>().encode() {
+        return "native".into();
+    }
+    match local_asset_id(location) {
+        Some(asset_id) => format!("asset {asset_id}"),
+        None => format!("{location:?}"),
+    }
+}
+
@@ -0,0 +1,92 @@
+use base58::ToBase58;
+use blake2::{Blake2b512, Digest};
+use subxt::{utils::AccountId32, OnlineClient};
+
+use crate::{error::AppError, free_balance, local, units::format_units, CustomConfig};
+
+// Roles an account can hold on an asset of `pallet-assets`
+pub async fn asset_roles(
+    api: &OnlineClient<CustomConfig>,
+    asset_id: u32,
+    account: &AccountId32,
+) -> Result<Option<Vec<&'static str>>, AppError> {
+    let asset = local::storage().assets().asset(asset_id);
+    let Some(details) = api.storage().at_latest().await?.fetch(&asset).await? else {
+        return Ok(None);
+    };
+
+    let roles = [
+        ("owner", &details.owner),
+        ("admin", &details.admin),
+        ("issuer", &details.issuer),
+        ("freezer", &details.freezer),
+    ];
+
+    Ok(Some(
+        roles
+            .into_iter()
+            .filter(|(_, holder)| *holder == account)
+            .map(|(role, _)| role)
+            .collect(),
+    ))
+}
+
+// SS58 address of the account for the network `prefix`. `AccountId32`
+// displays with the generic substrate prefix (42), which isn't the one the
+// wallets of most networks show.
+pub fn to_ss58(account: &AccountId32, prefix: u16) -> String {
+    let mut data = match prefix {
+        0..=63 => vec![prefix as u8],
+        _ => vec![
+            ((prefix & 0b1111_1100) >> 2) as u8 | 0b0100_0000,
+            (prefix >> 8) as u8 | ((prefix & 0b11) << 6) as u8,
+        ],
+    };
+    data.extend_from_slice(&account.0);
+
+    let checksum = Blake2b512::new().chain_update(b"SS58PRE").chain_update(&data).finalize();
+    data.extend_from_slice(&checksum[..2]);
+
+    data.to_base58()
+}
+
+// The `whoami` subcommand: who the run is going to sign as, the signer's
+// address for this network, its native balance and the roles it holds on the
+// asset, to check before anything privileged is submitted
+pub async fn whoami(
+    api: &OnlineClient<CustomConfig>,
+    account: AccountId32,
+    asset_id: u32,
+    native_token: &(u8, String),
+) -> Result<(), AppError> {
+    let (native_decimals, native_symbol) = native_token;
+
+    let prefix = api.constants().at(&local::constants().system().ss58_prefix())?;
+    let balance = free_balance(api, account.clone()).await?;
+
+    println!("Signer:  {} (SS58 prefix {prefix})", to_ss58(&account, prefix));
+    println!("Balance: {} {native_symbol}", format_units(balance, *native_decimals));
+    match asset_roles(api, asset_id, &account).await? {
+        None => println!("Roles:   the asset {asset_id} doesn't exist"),
+        Some(roles) if roles.is_empty() => println!("Roles:   none on the asset {asset_id}"),
+        Some(roles) => println!("Roles:   {} of the asset {asset_id}", roles.join(", ")),
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use subxt_signer::sr25519::dev;
+
+    use super::*;
+
+    // With the generic substrate prefix the address is the one `AccountId32`
+    // displays, and Polkadot's (0) moves `//Alice` to its well known `15oF4..`
+    #[test]
+    fn ss58_addresses() {
+        let alice: AccountId32 = dev::alice().public_key().into();
+        assert_eq!(to_ss58(&alice, 42), alice.to_string());
+        assert_eq!(to_ss58(&alice, 0), "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5");
+    }
+}
@@ -0,0 +1,125 @@
+// End-to-end run of the setup, a transfer paying its fee in the asset and a
+// swap, against a local node. Needs the `integration-tests` feature and the
+// path of an Asset Hub (or any runtime with the same pallets) node binary:
+//
+//   SUBSTRATE_NODE=./polkadot-omni-node \
+//   SUBSTRATE_NODE_ARGS="--chain asset-hub-westend-dev.json --dev-block-time 1000 --tmp" \
+//   cargo test --features integration-tests --test setup
+//
+// `SUBSTRATE_NODE_ARGS` defaults to `--dev --tmp`, the node gets a free
+// `--rpc-port` on top of them.
+#![cfg(feature = "integration-tests")]
+
+use std::{
+    net::{TcpListener, TcpStream},
+    process::{Child, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+use subxt::{
+    backend::{legacy::LegacyRpcMethods, rpc::RpcClient},
+    utils::{AccountId32, MultiAddress},
+    OnlineClient,
+};
+use subxt_signer::sr25519::dev;
+
+use asset_conversion_example::{
+    cli::{AssetConfig, SetupParams},
+    local, local_asset_location, native_location, setup, sign_and_send_transfer,
+    units::native_token_info,
+    AssetHubClient, CustomConfig, FeeMode, FeePayment, SubmitPolicy, FEE_BUFFER_PERCENT,
+};
+
+// How long the node gets to open its RPC port
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+const ASSET_ID: u32 = 4242;
+
+// The node process, killed when the test is done whichever way it ends
+struct Node {
+    child: Child,
+    url: String,
+}
+
+impl Node {
+    fn spawn() -> Self {
+        let binary = std::env::var("SUBSTRATE_NODE").expect("SUBSTRATE_NODE should point to the node binary");
+        let args = std::env::var("SUBSTRATE_NODE_ARGS").unwrap_or_else(|_| "--dev --tmp".into());
+
+        // Asking the OS for a free port, which is released for the node
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let child = Command::new(&binary)
+            .args(args.split_whitespace())
+            .arg(format!("--rpc-port={port}"))
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap_or_else(|e| panic!("could not start {binary}: {e}"));
+        let node = Self {
+            child,
+            url: format!("ws://127.0.0.1:{port}"),
+        };
+
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            assert!(started.elapsed() < STARTUP_TIMEOUT, "the node didn't open port {port}");
+            std::thread::sleep(Duration::from_millis(250));
+        }
+
+        node
+    }
+}
+
+impl Drop for Node {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+#[tokio::test]
+async fn setup_transfer_and_swap() {
+    let node = Node::spawn();
+    let rpc = RpcClient::from_url(&node.url).await.unwrap();
+    let api = OnlineClient::<CustomConfig>::from_rpc_client(rpc.clone()).await.unwrap();
+    let native_token = native_token_info(&LegacyRpcMethods::new(rpc)).await;
+    let alice = dev::alice();
+
+    let asset_config = AssetConfig {
+        id: ASSET_ID,
+        name: "Integration".into(),
+        symbol: "INT".into(),
+        decimals: 10,
+    };
+    let receipt = setup(api.clone(), &alice, &asset_config, &SetupParams::default(), &native_token)
+        .await
+        .unwrap();
+    assert!(receipt.events.has::<local::asset_conversion::events::LiquidityAdded>().unwrap());
+
+    let bob: MultiAddress<AccountId32, ()> = dev::bob().public_key().into();
+    let (receipt, payment) = sign_and_send_transfer(
+        api.clone(),
+        &alice,
+        &SubmitPolicy::default(),
+        bob,
+        1_000_000,
+        1,
+        0,
+        None,
+        FeeMode::Asset(Box::new(local_asset_location(ASSET_ID))),
+        false,
+        FEE_BUFFER_PERCENT,
+        native_token.0,
+    )
+    .await
+    .unwrap();
+    assert!(receipt.events.has::<local::asset_tx_payment::events::AssetTxFeePaid>().unwrap());
+    assert!(matches!(payment, FeePayment::Asset { amount, .. } if amount > 0));
+
+    let client = AssetHubClient::new(api, alice);
+    let receipt = client
+        .swap(vec![native_location(), local_asset_location(ASSET_ID)], 1_000_000, 1)
+        .await
+        .unwrap();
+    assert!(receipt.events.has::<local::asset_conversion::events::SwapExecuted>().unwrap());
+}